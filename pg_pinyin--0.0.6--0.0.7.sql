@@ -0,0 +1,1439 @@
+-- Upgrade pg_pinyin from 0.0.6 to 0.0.7.
+
+CREATE OR REPLACE FUNCTION public.pinyin_trgm_pattern(
+  value text
+)
+RETURNS text
+LANGUAGE c
+STABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_trgm_pattern_default_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_trgm_pattern(
+  value text,
+  initials boolean
+)
+RETURNS text
+LANGUAGE c
+STABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_trgm_pattern_with_initials_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_trgm_regex(
+  value text
+)
+RETURNS text
+LANGUAGE c
+STABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_trgm_regex_default_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_trgm_regex(
+  value text,
+  initials boolean
+)
+RETURNS text
+LANGUAGE c
+STABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_trgm_regex_with_initials_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_prs_start(
+  input internal,
+  len integer
+)
+RETURNS internal
+LANGUAGE c
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_prs_start_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_prs_gettoken(
+  state internal,
+  token internal,
+  token_len internal
+)
+RETURNS internal
+LANGUAGE c
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_prs_gettoken_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_prs_end(
+  _state internal
+)
+RETURNS void
+LANGUAGE c
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_prs_end_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_prs_lextype(
+  _unused internal
+)
+RETURNS internal
+LANGUAGE c
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_prs_lextype_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_dict_init(
+  options internal
+)
+RETURNS internal
+LANGUAGE c
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_dict_init_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_dict_lexize(
+  dictionary internal,
+  token internal,
+  token_len internal,
+  _state internal
+)
+RETURNS internal
+LANGUAGE c
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_dict_lexize_wrapper';
+
+CREATE TEXT SEARCH PARSER public.pinyin_parser (
+  START = public.pinyin_prs_start,
+  GETTOKEN = public.pinyin_prs_gettoken,
+  END = public.pinyin_prs_end,
+  LEXTYPES = public.pinyin_prs_lextype,
+  HEADLINE = pg_catalog.prsd_headline
+);
+
+CREATE TEXT SEARCH TEMPLATE public.pinyin_template (
+  INIT = public.pinyin_dict_init,
+  LEXIZE = public.pinyin_dict_lexize
+);
+
+CREATE TEXT SEARCH DICTIONARY public.pinyin_dict (
+  TEMPLATE = public.pinyin_template
+);
+
+CREATE TEXT SEARCH CONFIGURATION public.pinyin (
+  PARSER = public.pinyin_parser
+);
+
+ALTER TEXT SEARCH CONFIGURATION public.pinyin
+  ADD MAPPING FOR asciiword, han WITH public.pinyin_dict;
+
+CREATE OR REPLACE FUNCTION public.pinyin_romanize_tokens(
+  origin text
+)
+RETURNS TABLE (
+  ord integer,
+  source text,
+  char_start integer,
+  char_end integer,
+  byte_start integer,
+  byte_end integer,
+  pinyin text,
+  kind text
+)
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_romanize_tokens_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_romanize_tokens(
+  origin text,
+  suffix text
+)
+RETURNS TABLE (
+  ord integer,
+  source text,
+  char_start integer,
+  char_end integer,
+  byte_start integer,
+  byte_end integer,
+  pinyin text,
+  kind text
+)
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_romanize_tokens_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_romanize_tokens(
+  origin text,
+  suffix text,
+  options jsonb
+)
+RETURNS TABLE (
+  ord integer,
+  source text,
+  char_start integer,
+  char_end integer,
+  byte_start integer,
+  byte_end integer,
+  pinyin text,
+  kind text
+)
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_romanize_tokens_with_options_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_highlight(
+  origin text,
+  query text
+)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_highlight_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_highlight(
+  origin text,
+  query text,
+  start_tag text,
+  end_tag text
+)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_highlight_with_tags_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_highlight(
+  origin text,
+  query text,
+  start_tag text,
+  end_tag text,
+  suffix text
+)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_highlight_with_tags_and_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize_array(
+  origin text
+)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_array_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize_array(
+  origin text,
+  suffix text
+)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_array_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_word_romanize_array(
+  origin text
+)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_word_romanize_array_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_word_romanize_array(
+  origin text,
+  suffix text
+)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_word_romanize_array_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_romanize_jsonb(
+  origin text
+)
+RETURNS jsonb
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_romanize_jsonb_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_romanize_jsonb(
+  origin text,
+  suffix text
+)
+RETURNS jsonb
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_romanize_jsonb_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize(
+  origin text,
+  suffix text,
+  options jsonb
+)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_with_options_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_word_romanize(
+  origin text,
+  suffix text,
+  options jsonb
+)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_word_romanize_with_options_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_to_simplified(origin text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_to_simplified_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_to_traditional(origin text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_to_traditional_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_regex_phrase_patterns(
+  value text,
+  generated_pinyin boolean,
+  system text
+)
+RETURNS text[]
+LANGUAGE c
+STABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_regex_phrase_patterns_with_system_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_regex_phrase_patterns(
+  value text,
+  generated_pinyin boolean,
+  query_system text,
+  column_system text
+)
+RETURNS text[]
+LANGUAGE c
+STABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_regex_phrase_patterns_with_systems_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_slugify(origin text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_slugify_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_slugify(origin text, max_len integer)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_slugify_with_max_len_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_slugify(origin text, max_len integer, suffix text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_slugify_with_max_len_and_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_sort_key(origin text)
+RETURNS bytea
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_sort_key_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_sort_key(origin text, suffix text)
+RETURNS bytea
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_sort_key_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_sort_bucket(origin text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_sort_bucket_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_first_letter(origin text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_first_letter_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_first_letter(origin text, suffix text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_first_letter_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_first_letter(origin text, suffix text, options jsonb)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_first_letter_with_options_wrapper';
+
+CREATE TABLE IF NOT EXISTS pinyin.pinyin_surnames (
+  surname text PRIMARY KEY,
+  pinyin text NOT NULL
+);
+
+DROP TRIGGER IF EXISTS pinyin_surnames_bump_version ON pinyin.pinyin_surnames;
+CREATE TRIGGER pinyin_surnames_bump_version
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_surnames
+FOR EACH STATEMENT
+EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+
+INSERT INTO pinyin.pinyin_surnames (surname, pinyin) VALUES
+  ('万俟', 'mo qi'),
+  ('上官', 'shang guan'),
+  ('东方', 'dong fang'),
+  ('东郭', 'dong guo'),
+  ('令狐', 'ling hu'),
+  ('公孙', 'gong sun'),
+  ('公冶', 'gong ye'),
+  ('单于', 'chan yu'),
+  ('南宫', 'nan gong'),
+  ('司徒', 'si tu'),
+  ('司空', 'si kong'),
+  ('司马', 'si ma'),
+  ('呼延', 'hu yan'),
+  ('夏侯', 'xia hou'),
+  ('太史', 'tai shi'),
+  ('宇文', 'yu wen'),
+  ('尉迟', 'yu chi'),
+  ('慕容', 'mu rong'),
+  ('欧阳', 'ou yang'),
+  ('澹台', 'tan tai'),
+  ('濮阳', 'pu yang'),
+  ('独孤', 'du gu'),
+  ('申屠', 'shen tu'),
+  ('百里', 'bai li'),
+  ('皇甫', 'huang fu'),
+  ('第五', 'di wu'),
+  ('端木', 'duan mu'),
+  ('诸葛', 'zhu ge'),
+  ('赫连', 'he lian'),
+  ('轩辕', 'xuan yuan'),
+  ('钟离', 'zhong li'),
+  ('长孙', 'zhang sun'),
+  ('闻人', 'wen ren'),
+  ('乐正', 'yue zheng'),
+  ('萬俟', 'mo qi'),
+  ('東方', 'dong fang'),
+  ('東郭', 'dong guo'),
+  ('公孫', 'gong sun'),
+  ('單于', 'chan yu'),
+  ('司馬', 'si ma'),
+  ('尉遲', 'yu chi'),
+  ('歐陽', 'ou yang'),
+  ('獨孤', 'du gu'),
+  ('諸葛', 'zhu ge'),
+  ('赫連', 'he lian'),
+  ('軒轅', 'xuan yuan'),
+  ('鍾離', 'zhong li'),
+  ('長孫', 'zhang sun'),
+  ('聞人', 'wen ren'),
+  ('樂正', 'yue zheng'),
+  ('单', 'shan'),
+  ('單', 'shan'),
+  ('曾', 'zeng'),
+  ('仇', 'qiu'),
+  ('区', 'ou'),
+  ('區', 'ou'),
+  ('查', 'zha'),
+  ('朴', 'piao'),
+  ('解', 'xie'),
+  ('繁', 'po'),
+  ('覃', 'qin'),
+  ('翟', 'zhai'),
+  ('盖', 'ge'),
+  ('蓋', 'ge'),
+  ('员', 'yun'),
+  ('員', 'yun'),
+  ('尉', 'yu'),
+  ('乐', 'yue'),
+  ('樂', 'yue'),
+  ('召', 'shao'),
+  ('缪', 'miao'),
+  ('繆', 'miao'),
+  ('秘', 'bi'),
+  ('种', 'chong'),
+  ('種', 'chong'),
+  ('阚', 'kan'),
+  ('闞', 'kan'),
+  ('冼', 'xian'),
+  ('黑', 'he'),
+  ('能', 'nai'),
+  ('折', 'she'),
+  ('隗', 'wei'),
+  ('那', 'na'),
+  ('长', 'chang'),
+  ('長', 'chang'),
+  ('重', 'chong'),
+  ('都', 'du'),
+  ('炅', 'gui')
+ON CONFLICT (surname) DO NOTHING;
+
+CREATE OR REPLACE FUNCTION public.pinyin_name_romanize(origin text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_name_romanize_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_name_romanize(origin text, suffix text)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_name_romanize_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_name_romanize(origin text, suffix text, options jsonb)
+RETURNS text
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_name_romanize_with_options_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_distance(a text, b text)
+RETURNS double precision
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_distance_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_similarity(a text, b text)
+RETURNS double precision
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_similarity_wrapper';
+
+CREATE OPERATOR public.<~> (
+  LEFTARG = text,
+  RIGHTARG = text,
+  FUNCTION = public.pinyin_distance,
+  COMMUTATOR = OPERATOR(public.<~>)
+);
+
+CREATE OR REPLACE FUNCTION public.pinyin_homophones(origin text)
+RETURNS TABLE (
+  term text,
+  kind text
+)
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_homophones_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_homophones(origin text, suffix text)
+RETURNS TABLE (
+  term text,
+  kind text
+)
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_homophones_with_suffix_wrapper';
+
+CREATE TABLE IF NOT EXISTS pinyin.pinyin_word_frequency (
+  word text PRIMARY KEY,
+  frequency bigint NOT NULL CHECK (frequency >= 0)
+);
+
+DROP TRIGGER IF EXISTS pinyin_word_frequency_bump_version ON pinyin.pinyin_word_frequency;
+CREATE TRIGGER pinyin_word_frequency_bump_version
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_word_frequency
+FOR EACH STATEMENT
+EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+
+CREATE OR REPLACE FUNCTION public.pinyin_to_hanzi(origin text, "limit" integer)
+RETURNS TABLE (
+  rank integer,
+  candidate text
+)
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_to_hanzi_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_to_hanzi(origin text, "limit" integer, suffix text)
+RETURNS TABLE (
+  rank integer,
+  candidate text
+)
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_to_hanzi_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize(origins text[])
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_elements_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize(origins text[], suffix text)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_elements_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize(origins text[], suffix text, options jsonb)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_elements_with_options_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize_batch(origins text[])
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_batch_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize_batch(origins text[], suffix text)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_batch_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_char_romanize_batch(origins text[], suffix text, options jsonb)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_char_romanize_batch_with_options_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_word_romanize_batch(origins text[])
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_word_romanize_batch_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_word_romanize_batch(origins text[], suffix text)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_word_romanize_batch_with_suffix_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin_word_romanize_batch(origins text[], suffix text, options jsonb)
+RETURNS text[]
+LANGUAGE c
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_word_romanize_batch_with_options_wrapper';
+
+CREATE OR REPLACE FUNCTION public.pinyin__in_transaction_block()
+RETURNS boolean
+LANGUAGE c
+VOLATILE
+PARALLEL SAFE
+AS 'MODULE_PATHNAME', 'pinyin_in_transaction_block_internal_wrapper';
+
+CREATE OR REPLACE FUNCTION pinyin.romanize_column_value(
+  value text,
+  mode text,
+  suffix text DEFAULT ''
+)
+RETURNS text
+LANGUAGE plpgsql
+IMMUTABLE
+STRICT
+PARALLEL SAFE
+AS $$
+BEGIN
+  CASE lower(mode)
+    WHEN 'char' THEN
+      RETURN public.pinyin_char_romanize(value, suffix);
+    WHEN 'word' THEN
+      RETURN public.pinyin_word_romanize(value, suffix);
+    WHEN 'initials' THEN
+      RETURN array_to_string(
+        ARRAY(
+          SELECT left(syllable, 1)
+          FROM regexp_split_to_table(public.pinyin_word_romanize(value, suffix), ' ') AS syllable
+        ),
+        ''
+      );
+    ELSE
+      RAISE EXCEPTION 'romanize mode must be one of char, word, initials (got "%")', mode;
+  END CASE;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION pinyin.maintain_columns()
+RETURNS trigger
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  source_column text := TG_ARGV[0];
+  target_column text := TG_ARGV[1];
+  row_value jsonb;
+BEGIN
+  IF TG_WHEN <> 'BEFORE' OR TG_LEVEL <> 'ROW' OR TG_OP NOT IN ('INSERT', 'UPDATE') THEN
+    RAISE EXCEPTION 'pinyin.maintain_columns() must be a BEFORE INSERT OR UPDATE ... FOR EACH ROW trigger';
+  END IF;
+  IF TG_NARGS NOT BETWEEN 2 AND 4 THEN
+    RAISE EXCEPTION 'pinyin.maintain_columns() expects (source_column, target_column [, mode [, suffix]])';
+  END IF;
+
+  row_value := to_jsonb(NEW);
+  IF NOT row_value ? source_column THEN
+    RAISE EXCEPTION 'column "%" does not exist in %', source_column, TG_TABLE_NAME;
+  END IF;
+  IF NOT row_value ? target_column THEN
+    RAISE EXCEPTION 'column "%" does not exist in %', target_column, TG_TABLE_NAME;
+  END IF;
+
+  NEW := jsonb_populate_record(
+    NEW,
+    jsonb_build_object(
+      target_column,
+      pinyin.romanize_column_value(
+        row_value ->> source_column,
+        COALESCE(TG_ARGV[2], 'char'),
+        COALESCE(TG_ARGV[3], '')
+      )
+    )
+  );
+  RETURN NEW;
+END;
+$$;
+
+CREATE OR REPLACE PROCEDURE public.pinyin_backfill(
+  target regclass,
+  source_column text,
+  target_column text,
+  mode text DEFAULT 'char',
+  suffix text DEFAULT '',
+  batch_size integer DEFAULT 10000,
+  INOUT updated bigint DEFAULT 0
+)
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  partition regclass;
+  total_blocks bigint;
+  blocks_per_batch bigint;
+  first_block bigint;
+  batch_updated bigint;
+BEGIN
+  IF batch_size IS NULL OR batch_size <= 0 THEN
+    RAISE EXCEPTION 'pinyin_backfill batch_size must be positive';
+  END IF;
+  IF (SELECT relkind FROM pg_class WHERE oid = target) NOT IN ('r', 'p') THEN
+    RAISE EXCEPTION 'pinyin_backfill target % must be a table or a partitioned table', target;
+  END IF;
+  -- Fail on a bad mode before touching any row.
+  PERFORM pinyin.romanize_column_value('', mode, suffix);
+  -- COMMIT after each batch would otherwise fail with "invalid transaction
+  -- termination" once the first batch is done.
+  IF public.pinyin__in_transaction_block() THEN
+    RAISE EXCEPTION 'pinyin_backfill cannot run inside a transaction block'
+      USING ERRCODE = 'active_sql_transaction',
+            HINT = 'It commits after every batch; CALL it outside BEGIN ... COMMIT.';
+  END IF;
+
+  updated := 0;
+  -- A partitioned table has no storage of its own, so every leaf partition is
+  -- filled separately.
+  FOR partition IN
+    SELECT c.oid::regclass
+    FROM pg_class AS c
+    WHERE c.relkind = 'r'
+      AND (c.oid = target OR c.oid IN (SELECT relid FROM pg_partition_tree(target)))
+    ORDER BY c.oid::regclass::text
+  LOOP
+    -- Batches are block ranges sized from the planner statistics, so every
+    -- batch is a TID range scan; rows moved by earlier batches are skipped
+    -- because their value is already current.
+    total_blocks := pg_relation_size(partition) / current_setting('block_size')::bigint;
+    SELECT GREATEST(
+             1,
+             floor(
+               batch_size / CASE
+                 WHEN relpages > 0 AND reltuples > 0 THEN reltuples / relpages
+                 ELSE 100
+               END
+             )
+           )::bigint
+    INTO blocks_per_batch
+    FROM pg_class
+    WHERE oid = partition;
+
+    first_block := 0;
+    WHILE first_block < total_blocks LOOP
+      EXECUTE format(
+        'UPDATE %1$s AS t
+         SET %3$I = v.romanized
+         FROM (
+           SELECT ctid AS row_ctid, pinyin.romanize_column_value(%2$I::text, $1, $2) AS romanized
+           FROM %1$s
+           WHERE ctid >= $3 AND ctid < $4
+         ) AS v
+         WHERE t.ctid = v.row_ctid
+           AND t.%3$I IS DISTINCT FROM v.romanized',
+        partition,
+        source_column,
+        target_column
+      )
+      USING
+        mode,
+        suffix,
+        format('(%s,0)', first_block)::tid,
+        format('(%s,0)', first_block + blocks_per_batch)::tid;
+      GET DIAGNOSTICS batch_updated = ROW_COUNT;
+
+      updated := updated + batch_updated;
+      first_block := first_block + blocks_per_batch;
+      COMMIT;
+    END LOOP;
+  END LOOP;
+END;
+$$;
+
+CREATE TABLE IF NOT EXISTS pinyin.pinyin_dictionary_changes (
+  version bigint NOT NULL,
+  table_name text NOT NULL,
+  key text,
+  old_value text,
+  new_value text
+);
+
+CREATE INDEX IF NOT EXISTS pinyin_dictionary_changes_version_idx
+ON pinyin.pinyin_dictionary_changes (version);
+
+-- Records the rows touched by one statement under the version that the
+-- statement's `*_bump_version` trigger just set; triggers of the same event
+-- fire in name order, so `*_log_changes_*` always runs after the bump. A NULL
+-- key stands for the whole table (TRUNCATE); a NULL old/new value means the
+-- key did not exist before/after the statement.
+CREATE OR REPLACE FUNCTION pinyin.pinyin_dictionary_log_changes()
+RETURNS trigger
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  key_column text := TG_ARGV[0];
+  value_column text := TG_ARGV[1];
+  current_version bigint;
+BEGIN
+  IF current_setting('pinyin.skip_change_log', true) = 'on' THEN
+    RETURN NULL;
+  END IF;
+
+  SELECT version INTO current_version
+  FROM pinyin.pinyin_dictionary_meta
+  WHERE singleton;
+
+  IF TG_OP = 'TRUNCATE' THEN
+    INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key)
+    VALUES (current_version, TG_TABLE_NAME, NULL);
+  ELSIF TG_OP = 'INSERT' THEN
+    INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key, new_value)
+    SELECT current_version, TG_TABLE_NAME, to_jsonb(r) ->> key_column, to_jsonb(r) ->> value_column
+    FROM new_rows AS r;
+  ELSIF TG_OP = 'UPDATE' THEN
+    INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key, old_value, new_value)
+    SELECT current_version, TG_TABLE_NAME, COALESCE(n.key, o.key), o.value, n.value
+    FROM (
+      SELECT to_jsonb(r) ->> key_column AS key, to_jsonb(r) ->> value_column AS value
+      FROM old_rows AS r
+    ) AS o
+    FULL JOIN (
+      SELECT to_jsonb(r) ->> key_column AS key, to_jsonb(r) ->> value_column AS value
+      FROM new_rows AS r
+    ) AS n ON n.key = o.key;
+  ELSE
+    INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key, old_value)
+    SELECT current_version, TG_TABLE_NAME, to_jsonb(r) ->> key_column, to_jsonb(r) ->> value_column
+    FROM old_rows AS r;
+  END IF;
+  RETURN NULL;
+END;
+$$;
+
+-- Attaches the `*_bump_version` trigger and the `*_log_changes_*` triggers to
+-- a dictionary table, logging `key_column`/`value_column` of each changed row.
+CREATE OR REPLACE FUNCTION pinyin.pinyin_dictionary_track_table(
+  dictionary_table regclass,
+  key_column text,
+  value_column text
+)
+RETURNS void
+LANGUAGE plpgsql
+SET client_min_messages = warning
+AS $$
+DECLARE
+  table_name text;
+  event text;
+BEGIN
+  SELECT c.relname INTO table_name
+  FROM pg_class AS c
+  WHERE c.oid = dictionary_table;
+
+  EXECUTE format('DROP TRIGGER IF EXISTS %I ON %s', table_name || '_bump_version', dictionary_table);
+  EXECUTE format(
+    'CREATE TRIGGER %I
+     AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON %s
+     FOR EACH STATEMENT
+     EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version()',
+    table_name || '_bump_version',
+    dictionary_table
+  );
+
+  FOREACH event IN ARRAY ARRAY['insert', 'update', 'delete', 'truncate'] LOOP
+    EXECUTE format(
+      'DROP TRIGGER IF EXISTS %I ON %s',
+      table_name || '_log_changes_' || event,
+      dictionary_table
+    );
+    EXECUTE format(
+      'CREATE TRIGGER %I
+       AFTER %s ON %s
+       %s
+       FOR EACH STATEMENT
+       EXECUTE FUNCTION pinyin.pinyin_dictionary_log_changes(%L, %L)',
+      table_name || '_log_changes_' || event,
+      upper(event),
+      dictionary_table,
+      CASE event
+        WHEN 'insert' THEN 'REFERENCING NEW TABLE AS new_rows'
+        WHEN 'update' THEN 'REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows'
+        WHEN 'delete' THEN 'REFERENCING OLD TABLE AS old_rows'
+        ELSE ''
+      END,
+      key_column,
+      value_column
+    );
+  END LOOP;
+END;
+$$;
+
+SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_mapping', 'character', 'pinyin');
+SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_token', 'character', 'category');
+SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_words', 'word', 'pinyin');
+SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_surnames', 'surname', 'pinyin');
+SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_word_frequency', 'word', 'frequency');
+
+-- Canonical form of a user dictionary suffix, as used in overlay table names.
+CREATE OR REPLACE FUNCTION pinyin.dictionary_table_suffix(suffix text)
+RETURNS text
+LANGUAGE sql
+IMMUTABLE
+AS $$
+  SELECT CASE
+    WHEN btrim(suffix) = '' THEN ''
+    ELSE '_' || lower(ltrim(btrim(suffix), '_'))
+  END;
+$$;
+
+CREATE OR REPLACE FUNCTION public.pinyin_track_suffix_tables(suffix text)
+RETURNS integer
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  table_suffix text := pinyin.dictionary_table_suffix(suffix);
+  dictionary record;
+  tracked integer := 0;
+BEGIN
+  IF table_suffix IN ('', '_') THEN
+    RAISE EXCEPTION 'dictionary table suffix cannot be empty';
+  END IF;
+  IF table_suffix !~ '^_[a-z0-9_]+$' THEN
+    RAISE EXCEPTION 'dictionary table suffix must contain only [A-Za-z0-9_]';
+  END IF;
+
+  FOR dictionary IN
+    SELECT to_regclass(format('pinyin.%I', t.base_name || table_suffix)) AS overlay, t.key_column, t.value_column
+    FROM (
+      VALUES
+        ('pinyin_mapping', 'character', 'pinyin'),
+        ('pinyin_token', 'character', 'category'),
+        ('pinyin_words', 'word', 'pinyin'),
+        ('pinyin_surnames', 'surname', 'pinyin'),
+        ('pinyin_word_frequency', 'word', 'frequency')
+    ) AS t(base_name, key_column, value_column)
+  LOOP
+    CONTINUE WHEN dictionary.overlay IS NULL;
+    PERFORM pinyin.pinyin_dictionary_track_table(
+      dictionary.overlay,
+      dictionary.key_column,
+      dictionary.value_column
+    );
+    tracked := tracked + 1;
+  END LOOP;
+  RETURN tracked;
+END;
+$$;
+
+CREATE TABLE IF NOT EXISTS pinyin.pinyin_romanized_columns (
+  target regclass NOT NULL,
+  source_column text NOT NULL,
+  target_column text NOT NULL,
+  mode text NOT NULL DEFAULT 'char',
+  suffix text NOT NULL DEFAULT '',
+  refreshed_version bigint,
+  PRIMARY KEY (target, target_column)
+);
+
+-- Net changes between two dictionary versions: the first old and last new
+-- value of every key whose value differs, and a NULL-key row per truncated
+-- table.
+CREATE OR REPLACE FUNCTION pinyin.dictionary_net_changes(old_version bigint, new_version bigint)
+RETURNS TABLE (
+  table_name text,
+  key text,
+  old_value text,
+  new_value text
+)
+LANGUAGE sql
+STABLE
+AS $$
+  SELECT c.table_name, NULL::text, NULL::text, NULL::text
+  FROM pinyin.pinyin_dictionary_changes AS c
+  WHERE c.version > old_version
+    AND c.version <= new_version
+    AND c.key IS NULL
+  GROUP BY c.table_name
+
+  UNION ALL
+
+  SELECT n.table_name, n.key, n.old_value, n.new_value
+  FROM (
+    SELECT
+      c.table_name,
+      c.key,
+      (array_agg(c.old_value ORDER BY c.version))[1] AS old_value,
+      (array_agg(c.new_value ORDER BY c.version DESC))[1] AS new_value
+    FROM pinyin.pinyin_dictionary_changes AS c
+    WHERE c.version > old_version
+      AND c.version <= new_version
+      AND c.key IS NOT NULL
+    GROUP BY c.table_name, c.key
+  ) AS n
+  WHERE n.old_value IS DISTINCT FROM n.new_value;
+$$;
+
+-- Changed keys that can alter a column romanized in `mode` with `suffix`
+-- between two versions; NULL when one of those tables was truncated. Char
+-- mode ignores word changes.
+CREATE OR REPLACE FUNCTION pinyin.dictionary_changed_keys(
+  old_version bigint,
+  new_version bigint,
+  mode text,
+  suffix text DEFAULT ''
+)
+RETURNS text[]
+LANGUAGE sql
+STABLE
+AS $$
+  SELECT CASE
+    WHEN bool_or(n.key IS NULL) THEN NULL
+    ELSE COALESCE(array_agg(DISTINCT n.key), '{}')
+  END
+  FROM pinyin.dictionary_net_changes(old_version, new_version) AS n
+  CROSS JOIN LATERAL (SELECT pinyin.dictionary_table_suffix(suffix) AS table_suffix) AS s
+  WHERE n.table_name IN (
+      'pinyin_mapping',
+      'pinyin_token',
+      'pinyin_mapping' || s.table_suffix,
+      'pinyin_token' || s.table_suffix
+    )
+    OR (
+      lower(mode) <> 'char'
+      AND n.table_name IN ('pinyin_words', 'pinyin_words' || s.table_suffix)
+    );
+$$;
+
+CREATE OR REPLACE FUNCTION public.pinyin_dictionary_diff(old_version bigint, new_version bigint)
+RETURNS TABLE (
+  table_name text,
+  key text,
+  old_value text,
+  new_value text,
+  change text
+)
+LANGUAGE plpgsql
+STABLE
+AS $$
+#variable_conflict use_column
+BEGIN
+  IF old_version > new_version THEN
+    RAISE EXCEPTION 'pinyin_dictionary_diff old_version must not be greater than new_version';
+  END IF;
+
+  RETURN QUERY
+  SELECT
+    n.table_name,
+    n.key,
+    n.old_value,
+    n.new_value,
+    CASE
+      WHEN n.key IS NULL THEN 'truncated'
+      WHEN n.old_value IS NULL THEN 'added'
+      WHEN n.new_value IS NULL THEN 'removed'
+      ELSE 'changed'
+    END
+  FROM pinyin.dictionary_net_changes(old_version, new_version) AS n
+  ORDER BY n.table_name, n.key NULLS FIRST;
+END;
+$$;
+
+-- Rows whose source contains one of `keys` (every non-NULL source when `keys`
+-- is NULL), with the romanization under the current dictionary.
+CREATE OR REPLACE FUNCTION public.pinyin_affected_rows(
+  target regclass,
+  source_column text,
+  target_column text,
+  keys text[],
+  mode text DEFAULT 'char',
+  suffix text DEFAULT ''
+)
+RETURNS TABLE (
+  row_table regclass,
+  row_ctid tid,
+  source text,
+  stored text,
+  romanized text
+)
+LANGUAGE plpgsql
+STABLE
+AS $$
+BEGIN
+  PERFORM pinyin.romanize_column_value('', mode, suffix);
+  -- Only rows containing a changed key can romanize differently, and of
+  -- those only rows whose stored value differs need an update.
+  RETURN QUERY EXECUTE format(
+    'SELECT a.row_table, a.row_ctid, a.source, a.stored, a.romanized
+     FROM (
+       SELECT
+         tableoid::regclass AS row_table,
+         ctid AS row_ctid,
+         %2$I::text AS source,
+         %3$I::text AS stored,
+         pinyin.romanize_column_value(%2$I::text, $1, $2) AS romanized
+       FROM %1$s
+       WHERE %2$I IS NOT NULL
+         AND ($3 IS NULL OR EXISTS (
+           SELECT 1 FROM unnest($3) AS changed(key) WHERE strpos(%2$I::text, changed.key) > 0
+         ))
+     ) AS a
+     WHERE a.stored IS DISTINCT FROM a.romanized',
+    target,
+    source_column,
+    target_column
+  )
+  USING mode, suffix, keys;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION public.pinyin_affected_rows(
+  target regclass,
+  source_column text,
+  target_column text,
+  old_version bigint,
+  new_version bigint,
+  mode text DEFAULT 'char',
+  suffix text DEFAULT ''
+)
+RETURNS TABLE (
+  row_table regclass,
+  row_ctid tid,
+  source text,
+  stored text,
+  romanized text
+)
+LANGUAGE sql
+STABLE
+AS $$
+  SELECT a.row_table, a.row_ctid, a.source, a.stored, a.romanized
+  FROM public.pinyin_affected_rows(
+    target,
+    source_column,
+    target_column,
+    pinyin.dictionary_changed_keys(old_version, new_version, mode, suffix),
+    mode,
+    suffix
+  ) AS a;
+$$;
+
+CREATE OR REPLACE FUNCTION public.pinyin_register_column(
+  target regclass,
+  source_column text,
+  target_column text,
+  mode text DEFAULT 'char',
+  suffix text DEFAULT ''
+)
+RETURNS void
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  column_name text;
+BEGIN
+  PERFORM pinyin.romanize_column_value('', mode, suffix);
+  FOREACH column_name IN ARRAY ARRAY[source_column, target_column] LOOP
+    IF NOT EXISTS (
+      SELECT 1
+      FROM pg_attribute
+      WHERE attrelid = target
+        AND attname = column_name
+        AND attnum > 0
+        AND NOT attisdropped
+    ) THEN
+      RAISE EXCEPTION 'column "%" does not exist in %', column_name, target;
+    END IF;
+  END LOOP;
+  -- Overlay changes must be logged for the refresh to see them.
+  IF pinyin.dictionary_table_suffix(suffix) <> '' THEN
+    PERFORM public.pinyin_track_suffix_tables(suffix);
+  END IF;
+
+  -- A new registration has never been refreshed, so its first refresh
+  -- rewrites the whole column.
+  INSERT INTO pinyin.pinyin_romanized_columns AS r
+    (target, source_column, target_column, mode, suffix, refreshed_version)
+  VALUES (target, source_column, target_column, lower(mode), suffix, NULL)
+  ON CONFLICT ON CONSTRAINT pinyin_romanized_columns_pkey DO UPDATE
+  SET source_column = EXCLUDED.source_column,
+      mode = EXCLUDED.mode,
+      suffix = EXCLUDED.suffix,
+      refreshed_version = NULL;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION public.pinyin_unregister_column(target regclass, target_column text)
+RETURNS boolean
+LANGUAGE sql
+AS $$
+  WITH removed AS (
+    DELETE FROM pinyin.pinyin_romanized_columns AS r
+    WHERE r.target = $1 AND r.target_column = $2
+    RETURNING 1
+  )
+  SELECT EXISTS (SELECT 1 FROM removed);
+$$;
+
+-- Registered columns behind the current dictionary version, with the number
+-- of changed characters/words since their last refresh (NULL: full refresh).
+CREATE OR REPLACE FUNCTION public.pinyin_stale_columns()
+RETURNS TABLE (
+  target regclass,
+  source_column text,
+  target_column text,
+  refreshed_version bigint,
+  dictionary_version bigint,
+  changed_keys bigint
+)
+LANGUAGE sql
+STABLE
+AS $$
+  SELECT
+    r.target,
+    r.source_column,
+    r.target_column,
+    r.refreshed_version,
+    m.version,
+    CASE
+      WHEN r.refreshed_version IS NOT NULL THEN
+        cardinality(pinyin.dictionary_changed_keys(r.refreshed_version, m.version, r.mode, r.suffix))
+    END::bigint
+  FROM pinyin.pinyin_romanized_columns AS r
+  CROSS JOIN pinyin.pinyin_dictionary_meta AS m
+  WHERE m.singleton
+    AND (r.refreshed_version IS NULL OR r.refreshed_version < m.version)
+  ORDER BY r.target::text, r.target_column;
+$$;
+
+CREATE OR REPLACE PROCEDURE public.pinyin_refresh_columns(
+  batch_size integer DEFAULT 10000,
+  max_changed_keys integer DEFAULT 1000,
+  INOUT updated bigint DEFAULT 0
+)
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  current_version bigint;
+  registrations pinyin.pinyin_romanized_columns[];
+  registration pinyin.pinyin_romanized_columns;
+  changed_keys text[];
+  column_updated bigint;
+BEGIN
+  IF public.pinyin__in_transaction_block() THEN
+    RAISE EXCEPTION 'pinyin_refresh_columns cannot run inside a transaction block'
+      USING ERRCODE = 'active_sql_transaction',
+            HINT = 'It commits after every column; CALL it outside BEGIN ... COMMIT.';
+  END IF;
+
+  SELECT version INTO current_version
+  FROM pinyin.pinyin_dictionary_meta
+  WHERE singleton;
+
+  -- Registrations of dropped tables are skipped.
+  registrations := ARRAY(
+    SELECT r
+    FROM pinyin.pinyin_romanized_columns AS r
+    JOIN pg_class AS c ON c.oid = r.target
+    WHERE r.refreshed_version IS NULL OR r.refreshed_version < current_version
+    ORDER BY r.target::text, r.target_column
+  );
+
+  updated := 0;
+  FOREACH registration IN ARRAY registrations LOOP
+    changed_keys := NULL;
+    IF registration.refreshed_version IS NOT NULL THEN
+      changed_keys := pinyin.dictionary_changed_keys(
+        registration.refreshed_version,
+        current_version,
+        registration.mode,
+        registration.suffix
+      );
+    END IF;
+
+    column_updated := 0;
+    IF changed_keys IS NULL OR cardinality(changed_keys) > max_changed_keys THEN
+      CALL public.pinyin_backfill(
+        registration.target,
+        registration.source_column,
+        registration.target_column,
+        registration.mode,
+        registration.suffix,
+        batch_size,
+        column_updated
+      );
+    ELSIF cardinality(changed_keys) > 0 THEN
+      -- tableoid keeps ctids of different partitions apart.
+      EXECUTE format(
+        'UPDATE %1$s AS t
+         SET %2$I = a.romanized
+         FROM public.pinyin_affected_rows($1, $2, $3, $4, $5, $6) AS a
+         WHERE t.tableoid = a.row_table
+           AND t.ctid = a.row_ctid',
+        registration.target,
+        registration.target_column
+      )
+      USING
+        registration.target,
+        registration.source_column,
+        registration.target_column,
+        changed_keys,
+        registration.mode,
+        registration.suffix;
+      GET DIAGNOSTICS column_updated = ROW_COUNT;
+    END IF;
+
+    UPDATE pinyin.pinyin_romanized_columns AS r
+    SET refreshed_version = current_version
+    WHERE r.target = registration.target
+      AND r.target_column = registration.target_column;
+    updated := updated + column_updated;
+    COMMIT;
+  END LOOP;
+END;
+$$;
+
+-- Deletes logged changes up to `up_to_version`, by default everything every
+-- registered column has already caught up to. Registrations behind the pruned
+-- version are reset so their next refresh rewrites the whole column.
+CREATE OR REPLACE FUNCTION public.pinyin_prune_dictionary_changes(up_to_version bigint DEFAULT NULL)
+RETURNS bigint
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  prune_version bigint := up_to_version;
+  pruned bigint;
+BEGIN
+  IF prune_version IS NULL THEN
+    SELECT COALESCE(
+             (
+               SELECT min(r.refreshed_version)
+               FROM pinyin.pinyin_romanized_columns AS r
+               JOIN pg_class AS c ON c.oid = r.target
+             ),
+             m.version
+           )
+    INTO prune_version
+    FROM pinyin.pinyin_dictionary_meta AS m
+    WHERE m.singleton;
+  END IF;
+
+  DELETE FROM pinyin.pinyin_dictionary_changes AS c
+  WHERE c.version <= prune_version;
+  GET DIAGNOSTICS pruned = ROW_COUNT;
+
+  UPDATE pinyin.pinyin_romanized_columns AS r
+  SET refreshed_version = NULL
+  WHERE r.refreshed_version < prune_version;
+  RETURN pruned;
+END;
+$$;
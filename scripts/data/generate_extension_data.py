@@ -54,8 +54,13 @@ INITIALS = [
 VOWEL_TOKENS = {"a", "e", "i", "o", "u"}
 
 
+UMLAUT_VOWELS = {"ü": "v", "ǖ": "v", "ǘ": "v", "ǚ": "v", "ǜ": "v"}
+
+
 def normalize_tone(raw: str, normalizer: dict[str, str] | None) -> str:
-    text = raw.strip().lower()
+    # `ü` becomes `v` before the tone normalizer runs, which folds it to `u`
+    # and would make `nǚ` (女) read like `nǔ` (努).
+    text = "".join(UMLAUT_VOWELS.get(ch, ch) for ch in raw.strip().lower())
     if normalizer:
         out = "".join(normalizer.get(ch, ch) for ch in text)
     else:
@@ -150,11 +155,44 @@ def write_token_csv(path: Path, rows: list[tuple[str, int]]) -> None:
         writer.writerows(rows)
 
 
-def split_joined_pinyin(joined: str, syllables: set[str], lengths_desc: list[int]) -> tuple[str, bool]:
+def split_exact(text: str, syllables: set[str], lengths_desc: list[int], count: int) -> list[str] | None:
+    """Splits `text` into exactly `count` syllables, preferring longer ones
+    first, so `banv` for `吧女` reads `ba nv` rather than `ban v`."""
+    letters = "".join(ch for ch in text if ch != "'" and not ch.isspace())
+    failed: set[tuple[int, int]] = set()
+
+    def walk(start: int, remaining: int) -> list[str] | None:
+        if start == len(letters):
+            return [] if remaining == 0 else None
+        if remaining == 0 or (start, remaining) in failed:
+            return None
+        for width in lengths_desc:
+            candidate = letters[start : start + width]
+            if len(candidate) == width and candidate in syllables:
+                rest = walk(start + width, remaining - 1)
+                if rest is not None:
+                    return [candidate, *rest]
+        failed.add((start, remaining))
+        return None
+
+    return walk(0, count)
+
+
+def split_joined_pinyin(
+    joined: str,
+    syllables: set[str],
+    lengths_desc: list[int],
+    expected_count: int | None = None,
+) -> tuple[str, bool]:
     text = joined.strip().lower().replace("u:", "v").replace("ü", "v").replace("’", "'")
     if not text:
         return "", False
 
+    if expected_count:
+        exact = split_exact(text, syllables, lengths_desc, expected_count)
+        if exact is not None:
+            return " ".join(exact), False
+
     tokens = []
     i = 0
     had_fallback = False
@@ -223,7 +261,9 @@ def write_words_csv(
             if word in unique_words:
                 continue
 
-            segmented, used_fallback = split_joined_pinyin(joined, syllables, lengths_desc)
+            segmented, used_fallback = split_joined_pinyin(
+                joined, syllables, lengths_desc, len(word)
+            )
             if not segmented:
                 continue
 
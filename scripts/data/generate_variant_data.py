@@ -0,0 +1,240 @@
+#!/usr/bin/env python3
+"""
+Generate the Traditional/Simplified variant mapping.
+
+Two sources are supported:
+- OpenCC's dictionaries (`--opencc-dir` pointing at a directory with
+  `STCharacters.txt`, `STPhrases.txt`, `TSCharacters.txt` and
+  `TSPhrases.txt`; Apache License 2.0).
+- ICU's `Hans-Hant` / `Hant-Hans` transliterator rules from CLDR, read from
+  the system ICU library (the default; Unicode License v3). These are the
+  rules the bundled `sql/data/pinyin_variants.csv` was generated from.
+
+Output rows (`kind,source,target`):
+- ts_char   (Traditional character -> Simplified character)
+- ts_phrase (Traditional word -> Simplified word)
+- st_char   (Simplified character -> default Traditional character)
+- st_phrase (Simplified word -> Traditional word)
+
+Rows that change nothing are dropped: identity character rows, and phrase
+rows whose target the character mapping already produces. An identity
+phrase is kept when it stops the character mapping from converting a
+shared character (`皇后` stays `皇后` although `后` becomes `後`).
+"""
+
+from __future__ import annotations
+
+import argparse
+import csv
+import ctypes
+import ctypes.util
+import re
+from itertools import product
+from pathlib import Path
+
+CharMap = dict[str, str]
+PhraseMap = dict[str, str]
+
+ICU_RULE = re.compile(
+    r"^(?:\[(?P<before>[^\]]+)\])?"
+    r"\{?(?P<key>[^\s{}\[\]>]+)\}?"
+    r"(?:\[(?P<after_set>[^\]]+)\]|(?P<after>[^\s{}\[\]>]+))?"
+    r" > (?P<target>\S*)$"
+)
+
+
+def load_icu_rules(transliterator_id: str, library: str | None) -> list[str]:
+    path = library or ctypes.util.find_library("icui18n")
+    if not path:
+        raise SystemExit("ICU i18n library not found; pass --icu-library or --opencc-dir")
+    lib = ctypes.CDLL(path)
+    version = re.search(r"\.so\.(\d+)", ctypes.util.find_library("icui18n") or path)
+    suffix = f"_{version.group(1)}" if version else ""
+
+    open_u = getattr(lib, f"utrans_openU{suffix}")
+    open_u.restype = ctypes.c_void_p
+    open_u.argtypes = [
+        ctypes.c_void_p,
+        ctypes.c_int32,
+        ctypes.c_int,
+        ctypes.c_void_p,
+        ctypes.c_int32,
+        ctypes.c_void_p,
+        ctypes.POINTER(ctypes.c_int),
+    ]
+    to_rules = getattr(lib, f"utrans_toRules{suffix}")
+    to_rules.restype = ctypes.c_int32
+    to_rules.argtypes = [
+        ctypes.c_void_p,
+        ctypes.c_byte,
+        ctypes.c_void_p,
+        ctypes.c_int32,
+        ctypes.POINTER(ctypes.c_int),
+    ]
+
+    encoded = transliterator_id.encode("utf-16-le")
+    status = ctypes.c_int(0)
+    handle = open_u(encoded + b"\0\0", len(encoded) // 2, 0, None, 0, None, ctypes.byref(status))
+    if status.value > 0 or not handle:
+        raise SystemExit(f"ICU could not open transliterator {transliterator_id} ({status.value})")
+
+    capacity = 1 << 22
+    buffer = ctypes.create_string_buffer(capacity * 2)
+    status = ctypes.c_int(0)
+    length = to_rules(handle, 0, buffer, capacity, ctypes.byref(status))
+    if status.value > 0:
+        raise SystemExit(f"ICU could not export {transliterator_id} rules ({status.value})")
+
+    text = buffer.raw[: length * 2].decode("utf-16-le")
+    return [rule.strip() for rule in text.split(";") if rule.strip()]
+
+
+def parse_icu_rules(rules: list[str]) -> tuple[CharMap, PhraseMap]:
+    """Splits rules into characters and phrases; the first rule for a key wins,
+    and context rules (`[一二三]{余} > 餘`) are expanded into phrases."""
+    chars: CharMap = {}
+    phrases: PhraseMap = {}
+    context_rules = []
+
+    for rule in rules:
+        match = ICU_RULE.match(rule)
+        if not match:
+            raise SystemExit(f"unsupported ICU rule: {rule}")
+        if match["before"] or match["after_set"] or "{" in rule:
+            context_rules.append(match)
+            continue
+        source = match["key"] + (match["after"] or "")
+        target = match["target"]
+        table = chars if len(source) == 1 else phrases
+        table.setdefault(source, target)
+
+    def convert(value: str) -> str:
+        return "".join(chars.get(ch, ch) for ch in value)
+
+    for match in context_rules:
+        befores = list(match["before"] or "") or [""]
+        afters = list(match["after_set"] or "") or [match["after"] or ""]
+        for before, after in product(befores, afters):
+            source = before + match["key"] + after
+            phrases.setdefault(source, convert(before) + match["target"] + convert(after))
+
+    return chars, phrases
+
+
+def load_icu(library: str | None) -> tuple[CharMap, PhraseMap, CharMap, PhraseMap]:
+    st_chars, st_phrases = parse_icu_rules(load_icu_rules("Hans-Hant", library))
+    ts_chars, ts_phrases = parse_icu_rules(load_icu_rules("Hant-Hans", library))
+    return ts_chars, ts_phrases, st_chars, st_phrases
+
+
+def load_opencc_table(path: Path) -> dict[str, str]:
+    """OpenCC lines are `source<TAB>target [alternatives...]`; the first
+    target is the default."""
+    out: dict[str, str] = {}
+    with path.open("r", encoding="utf-8") as fh:
+        for line in fh:
+            line = line.strip()
+            if not line or line.startswith("#"):
+                continue
+            source, _, targets = line.partition("\t")
+            if targets:
+                out.setdefault(source, targets.split(" ")[0])
+    return out
+
+
+def load_opencc(directory: Path) -> tuple[CharMap, PhraseMap, CharMap, PhraseMap]:
+    return (
+        load_opencc_table(directory / "TSCharacters.txt"),
+        load_opencc_table(directory / "TSPhrases.txt"),
+        load_opencc_table(directory / "STCharacters.txt"),
+        load_opencc_table(directory / "STPhrases.txt"),
+    )
+
+
+def prune(chars: CharMap, phrases: PhraseMap) -> tuple[CharMap, PhraseMap]:
+    chars = {
+        source: target
+        for source, target in chars.items()
+        if len(source) == 1 and len(target) == 1 and source != target
+    }
+
+    def convert(value: str) -> str:
+        return "".join(chars.get(ch, ch) for ch in value)
+
+    phrases = {
+        source: target
+        for source, target in phrases.items()
+        if len(source) > 1 and target and convert(source) != target
+    }
+    return chars, phrases
+
+
+def write_variants_csv(
+    path: Path,
+    ts_chars: CharMap,
+    ts_phrases: PhraseMap,
+    st_chars: CharMap,
+    st_phrases: PhraseMap,
+) -> None:
+    path.parent.mkdir(parents=True, exist_ok=True)
+    with path.open("w", encoding="utf-8", newline="") as out_fh:
+        writer = csv.writer(out_fh, lineterminator="\n")
+        for kind, table in (
+            ("ts_char", ts_chars),
+            ("ts_phrase", ts_phrases),
+            ("st_char", st_chars),
+            ("st_phrase", st_phrases),
+        ):
+            for source, target in sorted(table.items()):
+                writer.writerow([kind, source, target])
+
+
+def parse_args() -> argparse.Namespace:
+    parser = argparse.ArgumentParser(
+        description="Generate pg_pinyin Traditional/Simplified variant CSV from OpenCC or ICU"
+    )
+    parser.add_argument(
+        "--opencc-dir",
+        help="Directory with OpenCC's STCharacters/STPhrases/TSCharacters/TSPhrases.txt",
+    )
+    parser.add_argument(
+        "--icu-library",
+        help="Path to libicui18n when it is not on the default library path",
+    )
+    parser.add_argument(
+        "--variants-out",
+        default="sql/data/pinyin_variants.csv",
+        help="Output path for variant mapping CSV",
+    )
+    return parser.parse_args()
+
+
+def main() -> int:
+    args = parse_args()
+
+    if args.opencc_dir:
+        opencc_dir = Path(args.opencc_dir)
+        if not opencc_dir.is_dir():
+            raise SystemExit(f"OpenCC dictionary directory does not exist: {opencc_dir}")
+        tables = load_opencc(opencc_dir)
+    else:
+        tables = load_icu(args.icu_library)
+
+    ts_chars, ts_phrases, st_chars, st_phrases = tables
+    ts_chars, ts_phrases = prune(ts_chars, ts_phrases)
+    st_chars, st_phrases = prune(st_chars, st_phrases)
+
+    variants_out = Path(args.variants_out)
+    write_variants_csv(variants_out, ts_chars, ts_phrases, st_chars, st_phrases)
+
+    print(f"[ok] ts_char rows: {len(ts_chars)}")
+    print(f"[ok] ts_phrase rows: {len(ts_phrases)}")
+    print(f"[ok] st_char rows: {len(st_chars)}")
+    print(f"[ok] st_phrase rows: {len(st_phrases)}")
+    print(f"[ok] wrote: {variants_out}")
+
+    return 0
+
+
+if __name__ == "__main__":
+    raise SystemExit(main())
@@ -37,3 +37,12 @@ else
 fi
 
 python3 "$ROOT_DIR/scripts/data/generate_extension_data.py" "${ARGS[@]}" "$@"
+
+VARIANT_ARGS=()
+if [[ -n "${OPENCC_DIR:-}" ]]; then
+  echo "[generate] variant source: $OPENCC_DIR"
+  VARIANT_ARGS+=(--opencc-dir "$OPENCC_DIR")
+fi
+python3 "$ROOT_DIR/scripts/data/generate_variant_data.py" \
+  --variants-out "$ROOT_DIR/sql/data/pinyin_variants.csv" \
+  ${VARIANT_ARGS[@]+"${VARIANT_ARGS[@]}"}
@@ -0,0 +1,129 @@
+pub fn match_phrase_syllables(syllables: &[&str], tokens: &[String]) -> Vec<bool> {
+    let mut matched = vec![false; syllables.len()];
+    if tokens.is_empty() {
+        return matched;
+    }
+
+    let mut idx = 0usize;
+    while idx < syllables.len() {
+        match match_phrase_at(syllables, idx, tokens) {
+            Some(span) => {
+                for flag in &mut matched[idx..idx + span] {
+                    *flag = true;
+                }
+                idx += span;
+            }
+            None => idx += 1,
+        }
+    }
+
+    matched
+}
+
+// Every syllable takes as many query tokens as still form a prefix of it, so
+// an ASCII run like `abc` can absorb the single-letter tokens `a`, `b`, `c`.
+fn match_phrase_at(syllables: &[&str], start: usize, tokens: &[String]) -> Option<usize> {
+    let mut syllable_idx = start;
+    let mut token_idx = 0usize;
+
+    while token_idx < tokens.len() {
+        let syllable = syllables.get(syllable_idx)?;
+        let mut consumed = String::new();
+        let mut taken = 0usize;
+
+        while let Some(token) = tokens.get(token_idx + taken) {
+            // Overlay readings may be non-ASCII (`lǜ`), so compare without
+            // slicing at a byte offset.
+            if !syllable[consumed.len()..].starts_with(token.as_str()) {
+                break;
+            }
+            consumed.push_str(token);
+            taken += 1;
+        }
+
+        if taken == 0 {
+            return None;
+        }
+        token_idx += taken;
+        syllable_idx += 1;
+    }
+
+    Some(syllable_idx - start)
+}
+
+pub fn wrap_byte_ranges(
+    origin: &str,
+    ranges: &[(usize, usize)],
+    start_tag: &str,
+    end_tag: &str,
+) -> String {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_unstable();
+
+    let mut out =
+        String::with_capacity(origin.len() + ranges.len() * (start_tag.len() + end_tag.len()));
+    let mut last = 0usize;
+    let mut iter = sorted.into_iter().peekable();
+
+    while let Some((start, mut end)) = iter.next() {
+        while let Some(&(next_start, next_end)) = iter.peek() {
+            if next_start > end {
+                break;
+            }
+            end = end.max(next_end);
+            iter.next();
+        }
+
+        out.push_str(&origin[last..start]);
+        out.push_str(start_tag);
+        out.push_str(&origin[start..end]);
+        out.push_str(end_tag);
+        last = end;
+    }
+
+    out.push_str(&origin[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn non_ascii_syllable_does_not_panic() {
+        assert_eq!(
+            match_phrase_syllables(&["lǜ", "se"], &tokens(&["lv", "s"])),
+            vec![false, false]
+        );
+        assert_eq!(
+            match_phrase_syllables(&["lǜ", "se"], &tokens(&["lǜ", "s"])),
+            vec![true, true]
+        );
+    }
+
+    #[test]
+    fn query_tokens_may_span_syllables() {
+        assert_eq!(
+            match_phrase_syllables(&["zheng", "shuang", "wo"], &tokens(&["zh", "shuang"])),
+            vec![true, true, false]
+        );
+        assert_eq!(
+            match_phrase_syllables(&["abc"], &tokens(&["a", "b", "c"])),
+            vec![true]
+        );
+        assert_eq!(match_phrase_syllables(&["zheng"], &[]), vec![false]);
+    }
+
+    #[test]
+    fn overlapping_ranges_are_wrapped_once() {
+        assert_eq!(
+            wrap_byte_ranges("郑爽我们", &[(3, 6), (0, 3), (9, 12)], "<b>", "</b>"),
+            "<b>郑爽</b>我<b>们</b>"
+        );
+        assert_eq!(wrap_byte_ranges("abc", &[], "<b>", "</b>"), "abc");
+    }
+}
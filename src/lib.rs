@@ -1,26 +1,51 @@
 #[cfg(feature = "extension")]
 pgrx::pg_module_magic!();
 
+pub mod highlight;
+pub mod numeral;
+pub mod reading_index;
 pub mod regex_phrase;
+pub mod romanization;
+pub mod script;
+pub mod similarity;
+pub mod sort_key;
+pub mod trgm_pattern;
+pub mod variant;
+pub mod zhuyin;
 
 #[cfg(feature = "extension")]
 mod extension {
+    use crate::highlight;
+    use crate::numeral::{self, NumeralMode};
+    use crate::reading_index::{self, ReadingIndex};
     use crate::regex_phrase::{self, RegexTokenDictionary};
+    use crate::romanization::{self, RomanizationSystem, SystemSyllables};
+    use crate::script::{self, PunctuationPolicy};
+    use crate::similarity;
+    use crate::sort_key;
+    use crate::trgm_pattern;
+    use crate::variant::VariantDictionary;
+    use crate::zhuyin::{self, OutputStyle};
 
     use std::collections::HashMap;
+    use std::ffi::{CStr, c_char, c_int};
     use std::fs;
     use std::mem;
     use std::process;
+    use std::ptr;
     use std::sync::{OnceLock, RwLock};
     use std::time::{SystemTime, UNIX_EPOCH};
 
-    use pgrx::datum::AnyElement;
+    use pgrx::datum::{AnyElement, Internal};
     use pgrx::prelude::*;
+    use unicode_normalization::UnicodeNormalization;
 
     const DICTIONARY_SCHEMA: &str = "pinyin";
     const EMBEDDED_MAPPING_CSV: &str = include_str!("../sql/data/pinyin_mapping.csv");
     const EMBEDDED_TOKEN_CSV: &str = include_str!("../sql/data/pinyin_token.csv");
     const EMBEDDED_WORDS_CSV: &str = include_str!("../sql/data/pinyin_words.csv");
+    const EMBEDDED_VARIANTS_CSV: &str = include_str!("../sql/data/pinyin_variants.csv");
+    const EMBEDDED_SURNAMES_CSV: &str = include_str!("../sql/data/pinyin_surnames.csv");
 
     #[derive(Default)]
     struct CharDictionaryCache {
@@ -36,6 +61,7 @@ mod extension {
         char_map: HashMap<String, String>,
         word_map: HashMap<String, String>,
         max_word_len: usize,
+        reading_index: OnceLock<ReadingIndex>,
     }
 
     #[derive(Default)]
@@ -45,13 +71,25 @@ mod extension {
         words_loaded: bool,
         word_map: HashMap<String, String>,
         max_word_len: usize,
+        reading_index: OnceLock<ReadingIndex>,
+    }
+
+    #[derive(Default)]
+    struct SurnameCacheEntry {
+        base_version: i64,
+        surnames: HashMap<String, String>,
+        max_len: usize,
     }
 
     static CHAR_DICTIONARY_CACHE: OnceLock<RwLock<CharDictionaryCache>> = OnceLock::new();
     static DICTIONARY_CACHE: OnceLock<RwLock<DictionaryCache>> = OnceLock::new();
     static REGEX_TOKEN_DICTIONARY: OnceLock<RegexTokenDictionary> = OnceLock::new();
+    static VARIANT_DICTIONARY: OnceLock<VariantDictionary> = OnceLock::new();
+    static WADE_GILES_SYLLABLES: OnceLock<SystemSyllables> = OnceLock::new();
+    static YALE_SYLLABLES: OnceLock<SystemSyllables> = OnceLock::new();
     static SUFFIX_DICTIONARY_CACHE: OnceLock<RwLock<HashMap<String, SuffixDictionaryCacheEntry>>> =
         OnceLock::new();
+    static SURNAME_CACHE: OnceLock<RwLock<HashMap<String, SurnameCacheEntry>>> = OnceLock::new();
 
     fn char_dictionary_cache() -> &'static RwLock<CharDictionaryCache> {
         CHAR_DICTIONARY_CACHE.get_or_init(|| RwLock::new(CharDictionaryCache::default()))
@@ -69,10 +107,32 @@ mod extension {
         })
     }
 
+    fn variant_dictionary() -> &'static VariantDictionary {
+        VARIANT_DICTIONARY.get_or_init(|| VariantDictionary::from_csv(EMBEDDED_VARIANTS_CSV))
+    }
+
+    fn system_syllables(system: RomanizationSystem) -> Option<&'static SystemSyllables> {
+        let cell = match system {
+            RomanizationSystem::Pinyin => return None,
+            RomanizationSystem::WadeGiles => &WADE_GILES_SYLLABLES,
+            RomanizationSystem::Yale => &YALE_SYLLABLES,
+        };
+        Some(cell.get_or_init(|| {
+            SystemSyllables::new(
+                system,
+                regex_phrase::tokens_from_pinyin_token_csv(EMBEDDED_TOKEN_CSV),
+            )
+        }))
+    }
+
     fn suffix_dictionary_cache() -> &'static RwLock<HashMap<String, SuffixDictionaryCacheEntry>> {
         SUFFIX_DICTIONARY_CACHE.get_or_init(|| RwLock::new(HashMap::new()))
     }
 
+    fn surname_cache() -> &'static RwLock<HashMap<String, SurnameCacheEntry>> {
+        SURNAME_CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+    }
+
     fn sql_literal(value: &str) -> String {
         format!("'{}'", value.replace('\'', "''"))
     }
@@ -115,6 +175,33 @@ mod extension {
         })
     }
 
+    fn fetch_word_frequencies(overlay_table: Option<&str>) -> HashMap<String, i64> {
+        let mut out = HashMap::new();
+        for table in std::iter::once("pinyin_word_frequency").chain(overlay_table) {
+            let query = format!("SELECT word, frequency FROM {DICTIONARY_SCHEMA}.{table}");
+            Spi::connect(|client| {
+                let rows = match client.select(&query, None, &[]) {
+                    Ok(rows) => rows,
+                    Err(err) => error!("SPI query failed: {err}. query={query}"),
+                };
+                for row in rows {
+                    let word = match row["word"].value::<String>() {
+                        Ok(Some(v)) => v,
+                        Ok(None) => continue,
+                        Err(err) => error!("SPI row parse failed for {table}.word: {err}"),
+                    };
+                    let frequency = match row["frequency"].value::<i64>() {
+                        Ok(Some(v)) => v,
+                        Ok(None) => continue,
+                        Err(err) => error!("SPI row parse failed for {table}.frequency: {err}"),
+                    };
+                    out.insert(word, frequency);
+                }
+            });
+        }
+        out
+    }
+
     fn fetch_overlayed_string_map(
         base_table: &str,
         key_col: &str,
@@ -244,13 +331,20 @@ mod extension {
         let truncate_sql = format!(
             "TRUNCATE TABLE {s}.pinyin_mapping; \
              TRUNCATE TABLE {s}.pinyin_token; \
-             TRUNCATE TABLE {s}.pinyin_words;",
+             TRUNCATE TABLE {s}.pinyin_words; \
+             TRUNCATE TABLE {s}.pinyin_surnames;",
             s = DICTIONARY_SCHEMA
         );
         if let Err(err) = Spi::run(&truncate_sql) {
             error!("failed truncating dictionary tables before seed: {err}");
         }
 
+        // The truncates above are logged as whole-table changes; logging every
+        // seeded key on top of that would only bloat the change log.
+        if let Err(err) = Spi::run("SELECT set_config('pinyin.skip_change_log', 'on', true)") {
+            error!("failed disabling dictionary change log during seed: {err}");
+        }
+
         let copy_attempt = try_copy_csv_to_table(
             &format!("{DICTIONARY_SCHEMA}.pinyin_mapping"),
             "character, pinyin",
@@ -272,12 +366,21 @@ mod extension {
                 "words",
                 EMBEDDED_WORDS_CSV,
             )
+        })
+        .and_then(|_| {
+            try_copy_csv_to_table(
+                &format!("{DICTIONARY_SCHEMA}.pinyin_surnames"),
+                "surname, pinyin",
+                "surnames",
+                EMBEDDED_SURNAMES_CSV,
+            )
         });
 
         if let Err(copy_err) = copy_attempt {
             let mapping_rows = parse_embedded_string_rows(EMBEDDED_MAPPING_CSV, "mapping");
             let token_rows = parse_embedded_token_rows(EMBEDDED_TOKEN_CSV);
             let word_rows = parse_embedded_string_rows(EMBEDDED_WORDS_CSV, "word");
+            let surname_rows = parse_embedded_string_rows(EMBEDDED_SURNAMES_CSV, "surname");
 
             if let Err(err) = Spi::run(&truncate_sql) {
                 error!("failed truncating dictionary tables for fallback insert: {err}");
@@ -296,6 +399,12 @@ mod extension {
                 "pinyin",
                 &word_rows,
             );
+            bulk_insert_string_rows(
+                &format!("{DICTIONARY_SCHEMA}.pinyin_surnames"),
+                "surname",
+                "pinyin",
+                &surname_rows,
+            );
 
             warning!("COPY-based dictionary seed failed, fallback to INSERT: {copy_err}");
         }
@@ -308,6 +417,10 @@ mod extension {
         if let Err(err) = Spi::run(&space_sql) {
             error!("failed ensuring space mapping row: {err}");
         }
+
+        if let Err(err) = Spi::run("SELECT set_config('pinyin.skip_change_log', 'off', true)") {
+            error!("failed re-enabling dictionary change log after seed: {err}");
+        }
     }
 
     fn fetch_dictionary_version() -> i64 {
@@ -373,6 +486,7 @@ mod extension {
             char_map,
             word_map,
             max_word_len,
+            reading_index: OnceLock::new(),
         }
     }
 
@@ -477,6 +591,10 @@ mod extension {
             .expect("suffix dictionary cache write lock poisoned");
         let cleared = cache.len() as i64;
         cache.clear();
+        surname_cache()
+            .write()
+            .expect("surname cache write lock poisoned")
+            .clear();
         cleared
     }
 
@@ -486,11 +604,58 @@ mod extension {
             None => return false,
         };
 
+        let surnames_removed = surname_cache()
+            .write()
+            .expect("surname cache write lock poisoned")
+            .remove(&canonical_suffix)
+            .is_some();
         let lock = suffix_dictionary_cache();
         let mut cache = lock
             .write()
             .expect("suffix dictionary cache write lock poisoned");
-        cache.remove(&canonical_suffix).is_some()
+        cache.remove(&canonical_suffix).is_some() || surnames_removed
+    }
+
+    // Base and suffix entries share one map; the base entry uses an empty key.
+    fn with_surname_cache<R>(
+        canonical_suffix: Option<&str>,
+        f: impl FnOnce(&SurnameCacheEntry) -> R,
+    ) -> R {
+        let cache_key = canonical_suffix.unwrap_or_default().to_string();
+        let base_version = fetch_dictionary_version();
+        let lock = surname_cache();
+
+        {
+            let cache = lock.read().expect("surname cache read lock poisoned");
+            let cached = cache
+                .get(&cache_key)
+                .filter(|entry| entry.base_version == base_version);
+            if let Some(entry) = cached {
+                return f(entry);
+            }
+        }
+
+        let overlay = overlay_table_name("pinyin_surnames", canonical_suffix);
+        let surnames =
+            fetch_overlayed_string_map("pinyin_surnames", "surname", "pinyin", overlay.as_deref());
+        let max_len = surnames
+            .keys()
+            .map(|surname| surname.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        {
+            let mut cache = lock.write().expect("surname cache write lock poisoned");
+            let entry = cache.entry(cache_key).or_default();
+            if entry.base_version != base_version {
+                *entry = SurnameCacheEntry {
+                    base_version,
+                    surnames,
+                    max_len,
+                };
+            }
+            f(entry)
+        }
     }
 
     fn with_suffix_char_cache<R>(
@@ -525,6 +690,7 @@ mod extension {
                     words_loaded: false,
                     word_map: HashMap::new(),
                     max_word_len: 0,
+                    reading_index: OnceLock::new(),
                 };
             }
             f(entry)
@@ -564,16 +730,159 @@ mod extension {
                     words_loaded: true,
                     word_map,
                     max_word_len,
+                    reading_index: OnceLock::new(),
                 };
             }
             f(entry)
         }
     }
 
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+    enum Normalization {
+        #[default]
+        None,
+        Nfkc,
+    }
+
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+    enum LetterCase {
+        #[default]
+        Lower,
+        Upper,
+        Title,
+    }
+
+    /// Whether the output separator goes between syllables or only between
+    /// words (`zhengshuang abc`).
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+    enum JoinMode {
+        #[default]
+        Syllable,
+        Word,
+    }
+
+    #[derive(Clone, Debug)]
+    struct RomanizeOptions {
+        normalize: Normalization,
+        fold_latin: bool,
+        punctuation: PunctuationPolicy,
+        variant_fallback: bool,
+        style: OutputStyle,
+        system: RomanizationSystem,
+        separator: String,
+        case: LetterCase,
+        join: JoinMode,
+        numerals: NumeralMode,
+    }
+
+    impl Default for RomanizeOptions {
+        fn default() -> Self {
+            Self {
+                normalize: Normalization::default(),
+                fold_latin: false,
+                punctuation: PunctuationPolicy::default(),
+                variant_fallback: false,
+                style: OutputStyle::default(),
+                system: RomanizationSystem::default(),
+                separator: " ".to_string(),
+                case: LetterCase::default(),
+                join: JoinMode::default(),
+                numerals: NumeralMode::default(),
+            }
+        }
+    }
+
+    fn parse_romanization_system(value: &str) -> RomanizationSystem {
+        RomanizationSystem::parse(value).unwrap_or_else(|| {
+            error!("romanization system must be one of pinyin, wade_giles, yale")
+        })
+    }
+
+    fn parse_romanize_options(options: &pgrx::JsonB) -> RomanizeOptions {
+        let serde_json::Value::Object(entries) = &options.0 else {
+            error!("romanize options must be a jsonb object");
+        };
+
+        let mut parsed = RomanizeOptions::default();
+        for (key, value) in entries {
+            let text = || match value {
+                serde_json::Value::String(text) => text.as_str(),
+                _ => error!("romanize option \"{key}\" must be a string"),
+            };
+            match key.as_str() {
+                "fold_latin" => {
+                    parsed.fold_latin = match value {
+                        serde_json::Value::Bool(flag) => *flag,
+                        _ => error!("romanize option \"fold_latin\" must be a boolean"),
+                    };
+                }
+                "variant_fallback" => {
+                    parsed.variant_fallback = match value {
+                        serde_json::Value::Bool(flag) => *flag,
+                        _ => error!("romanize option \"variant_fallback\" must be a boolean"),
+                    };
+                }
+                "normalize" => {
+                    parsed.normalize = match text().trim().to_ascii_lowercase().as_str() {
+                        "nfkc" => Normalization::Nfkc,
+                        "none" => Normalization::None,
+                        _ => error!("romanize option \"normalize\" must be one of nfkc, none"),
+                    };
+                }
+                "punctuation" => {
+                    parsed.punctuation = PunctuationPolicy::parse(text()).unwrap_or_else(|| {
+                        error!(
+                            "romanize option \"punctuation\" must be one of drop, keep, separator"
+                        )
+                    });
+                }
+                "style" => {
+                    parsed.style = OutputStyle::parse(text()).unwrap_or_else(|| {
+                        error!("romanize option \"style\" must be one of pinyin, zhuyin")
+                    });
+                }
+                "system" => {
+                    parsed.system = parse_romanization_system(text());
+                }
+                "numerals" => {
+                    parsed.numerals = NumeralMode::parse(text()).unwrap_or_else(|| {
+                        error!(
+                            "romanize option \"numerals\" must be one of keep, read, digits, arabic"
+                        )
+                    });
+                }
+                "separator" => {
+                    parsed.separator = text().to_string();
+                }
+                "case" => {
+                    parsed.case = match text().trim().to_ascii_lowercase().as_str() {
+                        "lower" => LetterCase::Lower,
+                        "upper" => LetterCase::Upper,
+                        "title" => LetterCase::Title,
+                        _ => error!("romanize option \"case\" must be one of lower, upper, title"),
+                    };
+                }
+                "join" => {
+                    parsed.join = match text().trim().to_ascii_lowercase().as_str() {
+                        "syllable" => JoinMode::Syllable,
+                        "word" => JoinMode::Word,
+                        _ => error!("romanize option \"join\" must be one of syllable, word"),
+                    };
+                }
+                _ => error!("unrecognized romanize option \"{key}\""),
+            }
+        }
+        if parsed.style == OutputStyle::Zhuyin && parsed.system != RomanizationSystem::Pinyin {
+            error!("romanize options \"style\" zhuyin and \"system\" cannot be combined");
+        }
+        parsed
+    }
+
     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
     enum PieceKind {
         AsciiRun,
         Space,
+        Punctuation,
         Other,
     }
 
@@ -581,15 +890,158 @@ mod extension {
     struct Piece {
         value: String,
         kind: PieceKind,
+        start: usize,
+        end: usize,
+    }
+
+    const LATIN_LETTER_FOLDS: [(char, &str); 16] = [
+        ('ß', "ss"),
+        ('æ', "ae"),
+        ('Æ', "AE"),
+        ('œ', "oe"),
+        ('Œ', "OE"),
+        ('ø', "o"),
+        ('Ø', "O"),
+        ('đ', "d"),
+        ('Đ', "D"),
+        ('ð', "d"),
+        ('Ð', "D"),
+        ('ł', "l"),
+        ('Ł', "L"),
+        ('þ', "th"),
+        ('Þ', "TH"),
+        ('ı', "i"),
+    ];
+
+    fn is_combining_mark(ch: char) -> bool {
+        ('\u{0300}'..='\u{036F}').contains(&ch)
+    }
+
+    // Accented Latin letters (including pinyin tone marks and `ü`) fold to
+    // their ASCII base letter; stray combining marks are removed so that a
+    // decomposed `zhe\u{300}ng` still forms one ASCII run.
+    fn push_folded_latin(ch: char, out: &mut impl Extend<char>) {
+        if is_combining_mark(ch) {
+            return;
+        }
+
+        if let Some((_, folded)) = LATIN_LETTER_FOLDS.iter().find(|(letter, _)| *letter == ch) {
+            out.extend(folded.chars());
+            return;
+        }
+
+        let mut decomposed = std::iter::once(ch).nfd();
+        match decomposed.next() {
+            Some(base) if base.is_ascii_alphabetic() && decomposed.all(is_combining_mark) => {
+                out.extend(std::iter::once(base));
+            }
+            _ => out.extend(std::iter::once(ch)),
+        }
+    }
+
+    fn normalize_char(ch: char, options: &RomanizeOptions, out: &mut Vec<char>) {
+        if ch.is_ascii() {
+            out.push(ch);
+            return;
+        }
+
+        let normalized: Vec<char> = match options.normalize {
+            Normalization::Nfkc => std::iter::once(ch).nfkc().collect(),
+            Normalization::None => vec![ch],
+        };
+        for normalized_ch in normalized {
+            if options.fold_latin && !normalized_ch.is_ascii() {
+                push_folded_latin(normalized_ch, out);
+            } else {
+                out.push(normalized_ch);
+            }
+        }
+    }
+
+    // Normalization is applied one source character at a time, so every
+    // normalized character still maps back to the source range it came from.
+    fn normalized_chars(input: &str, options: &RomanizeOptions) -> Vec<(usize, usize, char)> {
+        let mut chars = Vec::with_capacity(input.len());
+        let mut buffer = Vec::new();
+        for (start, ch) in input.char_indices() {
+            let end = start + ch.len_utf8();
+            buffer.clear();
+            normalize_char(ch, options, &mut buffer);
+            chars.extend(buffer.iter().map(|&normalized| (start, end, normalized)));
+        }
+        chars
+    }
+
+    fn normalize_tokens(tokens: &mut [String], options: &RomanizeOptions) {
+        for token in tokens.iter_mut() {
+            if !token.is_ascii() {
+                *token = normalized_chars(token, options)
+                    .into_iter()
+                    .map(|(_, _, ch)| ch)
+                    .collect();
+            }
+        }
+    }
+
+    // Numeral runs are rewritten after normalization; every replacement
+    // character keeps the source range of the whole run.
+    fn convert_numerals(
+        chars: Vec<(usize, usize, char)>,
+        mode: NumeralMode,
+    ) -> Vec<(usize, usize, char)> {
+        if mode == NumeralMode::Keep {
+            return chars;
+        }
+        let in_run = |ch: char| match mode {
+            NumeralMode::Arabic => numeral::is_chinese_numeral(ch),
+            _ => ch.is_ascii_digit(),
+        };
+
+        let mut out = Vec::with_capacity(chars.len());
+        let mut idx = 0usize;
+        while idx < chars.len() {
+            if !in_run(chars[idx].2) {
+                out.push(chars[idx]);
+                idx += 1;
+                continue;
+            }
+
+            let end = (idx..chars.len())
+                .find(|&end| !in_run(chars[end].2))
+                .unwrap_or(chars.len());
+            let run: String = chars[idx..end].iter().map(|&(_, _, ch)| ch).collect();
+            let converted = match mode {
+                NumeralMode::Read => Some(numeral::arabic_to_chinese(&run)),
+                NumeralMode::Digits => Some(numeral::digits_to_chinese(&run)),
+                NumeralMode::Arabic => numeral::chinese_to_arabic(&run),
+                NumeralMode::Keep => None,
+            };
+            match converted {
+                Some(converted) => {
+                    let (start, stop) = (chars[idx].0, chars[end - 1].1);
+                    out.extend(converted.chars().map(|ch| (start, stop, ch)));
+                }
+                None => out.extend_from_slice(&chars[idx..end]),
+            }
+            idx = end;
+        }
+        out
     }
 
-    fn split_input(input: &str) -> Vec<Piece> {
+    fn split_input(input: &str, options: &RomanizeOptions) -> Vec<Piece> {
         let mut pieces = Vec::new();
         let mut ascii_run = String::new();
+        let mut ascii_start = 0usize;
+        let mut ascii_end = 0usize;
 
-        for ch in input.chars() {
+        let chars = convert_numerals(normalized_chars(input, options), options.numerals);
+        for (start, end, ch) in chars {
             if ch.is_ascii_alphanumeric() {
+                if ascii_run.is_empty() {
+                    ascii_start = start;
+                }
                 ascii_run.push(ch);
+                ascii_end = end;
                 continue;
             }
 
@@ -597,6 +1049,8 @@ mod extension {
                 pieces.push(Piece {
                     value: mem::take(&mut ascii_run),
                     kind: PieceKind::AsciiRun,
+                    start: ascii_start,
+                    end: ascii_end,
                 });
             }
 
@@ -604,9 +1058,13 @@ mod extension {
                 value: ch.to_string(),
                 kind: if ch.is_whitespace() {
                     PieceKind::Space
+                } else if script::is_punctuation(ch) {
+                    PieceKind::Punctuation
                 } else {
                     PieceKind::Other
                 },
+                start,
+                end,
             });
         }
 
@@ -614,6 +1072,8 @@ mod extension {
             pieces.push(Piece {
                 value: ascii_run,
                 kind: PieceKind::AsciiRun,
+                start: ascii_start,
+                end: ascii_end,
             });
         }
 
@@ -660,24 +1120,21 @@ mod extension {
 
         let mut chars = token.chars();
         match (chars.next(), chars.next()) {
-            (Some(ch), None) => !ch.is_whitespace() && !ch.is_ascii_alphanumeric(),
+            (Some(ch), None) => script::is_han_char(ch),
             _ => false,
         }
     }
 
-    fn is_han_char(ch: char) -> bool {
-        !ch.is_whitespace() && !ch.is_ascii_alphanumeric()
-    }
-
     fn is_han_phrase(token: &str) -> bool {
-        !token.is_empty() && token.chars().all(is_han_char)
+        script::is_han_text(token)
     }
 
     fn romanize_plain_text_with_char_map(
         origin: &str,
         char_map: &HashMap<String, String>,
+        options: &RomanizeOptions,
     ) -> String {
-        let pieces = split_input(origin);
+        let pieces = split_input(origin, options);
         let mut out = String::new();
         let mut last_is_space = true;
 
@@ -693,6 +1150,15 @@ mod extension {
                         last_is_space = true;
                     }
                 }
+                PieceKind::Punctuation => {
+                    if options.punctuation.keeps_token() {
+                        out.push_str(&piece.value);
+                        last_is_space = false;
+                    } else if !last_is_space {
+                        out.push(' ');
+                        last_is_space = true;
+                    }
+                }
                 PieceKind::Other => {
                     if char_map.contains_key(&piece.value) {
                         out.push_str(&piece.value);
@@ -745,12 +1211,65 @@ mod extension {
         generated_pinyin: bool,
     ) -> Option<Vec<String>> {
         regex_phrase::pinyin_regex_phrase_patterns(
-            value,
+            &zhuyin::zhuyin_to_pinyin(value),
             generated_pinyin,
             regex_token_dictionary(),
         )
     }
 
+    fn pinyin_regex_phrase_patterns_with_systems_impl(
+        value: &str,
+        generated_pinyin: bool,
+        query_system: &str,
+        column_system: &str,
+    ) -> Option<Vec<String>> {
+        let query_system = parse_romanization_system(query_system);
+        let column_system = parse_romanization_system(column_system);
+        let value = match system_syllables(query_system) {
+            Some(table) => table.query_to_pinyin(value),
+            None => zhuyin::zhuyin_to_pinyin(value).into_owned(),
+        };
+
+        let Some(table) = system_syllables(column_system) else {
+            return regex_phrase::pinyin_regex_phrase_patterns(
+                &value,
+                generated_pinyin,
+                regex_token_dictionary(),
+            );
+        };
+        let Some(tokens) = regex_phrase::pinyin_query_tokens(&value, regex_token_dictionary())
+        else {
+            return Some(Vec::new());
+        };
+
+        // Each pinyin token becomes the spellings of every syllable it prefixes.
+        let patterns = tokens
+            .into_iter()
+            .map(|token| {
+                let spellings = table.prefix_spellings(&token);
+                let body = match spellings.as_slice() {
+                    [] => token,
+                    [spelling] => spelling.clone(),
+                    _ => format!("({})", spellings.join("|")),
+                };
+                if generated_pinyin {
+                    format!(r".*\|{body}.*")
+                } else {
+                    format!("{body}.*")
+                }
+            })
+            .collect();
+        Some(patterns)
+    }
+
+    fn pinyin_trgm_pattern_impl(value: &str, initials: bool) -> Option<String> {
+        trgm_pattern::pinyin_trgm_like_pattern(value, initials, regex_token_dictionary())
+    }
+
+    fn pinyin_trgm_regex_impl(value: &str, initials: bool) -> Option<String> {
+        trgm_pattern::pinyin_trgm_regex(value, initials, regex_token_dictionary())
+    }
+
     fn romanize_token_list(json_text: String) -> Vec<String> {
         match serde_json::from_str::<Vec<String>>(&json_text) {
             Ok(tokens) => tokens
@@ -805,39 +1324,126 @@ mod extension {
         }
     }
 
-    fn pinyin_char_romanize_with_char_map(
-        origin: &str,
-        char_map: &HashMap<String, String>,
+    // Only dictionary readings are restyled; ASCII runs and passthrough text
+    // keep their original form.
+    fn apply_output_style(
+        romanized: String,
+        from_dictionary: bool,
+        options: &RomanizeOptions,
     ) -> String {
-        let romanized_text = romanize_plain_text_with_char_map(origin, char_map);
-        let tokens = tokenize_plain(&romanized_text);
+        if !from_dictionary {
+            return romanized;
+        }
+        match (options.style, options.system) {
+            (OutputStyle::Zhuyin, _) => zhuyin::phrase_to_zhuyin(&romanized),
+            (_, RomanizationSystem::Pinyin) => romanized,
+            (_, system) => romanization::phrase_from_pinyin(&romanized, system),
+        }
+    }
 
-        if tokens.is_empty() {
-            return String::new();
+    fn apply_letter_case(piece: &str, case: LetterCase) -> String {
+        match case {
+            LetterCase::Lower => piece.to_string(),
+            LetterCase::Upper => piece.to_uppercase(),
+            LetterCase::Title => {
+                let mut chars = piece.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            }
+        }
+    }
+
+    /// Joins romanized units (one word or character each, syllables separated
+    /// by single spaces) with the separator, join mode and casing options.
+    fn join_romanized_units(units: &[String], options: &RomanizeOptions) -> String {
+        let mut pieces = Vec::with_capacity(units.len());
+        for unit in units {
+            match options.join {
+                JoinMode::Syllable => pieces.extend(
+                    unit.split(' ')
+                        .map(|syllable| apply_letter_case(syllable, options.case)),
+                ),
+                JoinMode::Word => {
+                    pieces.push(apply_letter_case(&unit.replace(' ', ""), options.case))
+                }
+            }
         }
+        pieces.join(&options.separator)
+    }
+
+    fn pinyin_char_romanize_parts_with_char_map(
+        origin: &str,
+        char_map: &HashMap<String, String>,
+        options: &RomanizeOptions,
+    ) -> Vec<String> {
+        let romanized_text = romanize_plain_text_with_char_map(origin, char_map, options);
+        let tokens = tokenize_plain(&romanized_text);
 
         let mut out = Vec::with_capacity(tokens.len());
         for token in tokens {
-            out.push(map_token(&token, char_map));
+            if !options.punctuation.emits_token() && script::is_punctuation_text(&token) {
+                continue;
+            }
+            let from_dictionary = char_map.contains_key(&token);
+            out.push(apply_output_style(
+                map_token(&token, char_map),
+                from_dictionary,
+                options,
+            ));
         }
-        out.join(" ")
+        out
     }
 
     fn pinyin_char_romanize_impl(origin: &str) -> String {
-        with_char_dictionary_cache(|char_map| pinyin_char_romanize_with_char_map(origin, char_map))
+        pinyin_char_romanize_array_impl(origin, None, &RomanizeOptions::default()).join(" ")
     }
 
     fn pinyin_char_romanize_with_suffix_impl(origin: &str, suffix: &str) -> String {
-        match canonicalize_table_suffix(suffix) {
+        pinyin_char_romanize_array_impl(origin, Some(suffix), &RomanizeOptions::default()).join(" ")
+    }
+
+    fn pinyin_char_romanize_array_impl(
+        origin: &str,
+        suffix: Option<&str>,
+        options: &RomanizeOptions,
+    ) -> Vec<String> {
+        match suffix.and_then(canonicalize_table_suffix) {
             Some(canonical_suffix) => with_suffix_char_cache(&canonical_suffix, |entry| {
-                pinyin_char_romanize_with_char_map(origin, &entry.char_map)
+                pinyin_char_romanize_parts_with_char_map(origin, &entry.char_map, options)
             }),
             None => with_char_dictionary_cache(|char_map| {
-                pinyin_char_romanize_with_char_map(origin, char_map)
+                pinyin_char_romanize_parts_with_char_map(origin, char_map, options)
             }),
         }
     }
 
+    // One cache lookup for the whole array; NULL elements stay NULL.
+    fn pinyin_char_romanize_batch_impl(
+        origins: Array<'_, &str>,
+        suffix: Option<&str>,
+        options: &RomanizeOptions,
+    ) -> Vec<Option<String>> {
+        let romanize_all = |char_map: &HashMap<String, String>| -> Vec<Option<String>> {
+            origins
+                .iter()
+                .map(|origin| {
+                    let parts =
+                        pinyin_char_romanize_parts_with_char_map(origin?, char_map, options);
+                    Some(join_romanized_units(&parts, options))
+                })
+                .collect()
+        };
+
+        match suffix.and_then(canonicalize_table_suffix) {
+            Some(canonical_suffix) => {
+                with_suffix_char_cache(&canonical_suffix, |entry| romanize_all(&entry.char_map))
+            }
+            None => with_char_dictionary_cache(romanize_all),
+        }
+    }
+
     fn map_word_fallback(token: &str, char_map: &HashMap<String, String>) -> String {
         if token.chars().all(|ch| ch.is_ascii_alphanumeric()) {
             return token.to_ascii_lowercase();
@@ -858,23 +1464,87 @@ mod extension {
         parts.join(" ")
     }
 
-    fn romanize_word_tokens_with_maps(
-        mut tokens: Vec<String>,
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    enum SegmentKind {
+        Word,
+        Char,
+        Passthrough,
+    }
+
+    impl SegmentKind {
+        fn as_str(self) -> &'static str {
+            match self {
+                SegmentKind::Word => "word",
+                SegmentKind::Char => "char",
+                SegmentKind::Passthrough => "passthrough",
+            }
+        }
+    }
+
+    struct WordSegment {
+        start: usize,
+        end: usize,
+        romanized: String,
+        kind: SegmentKind,
+    }
+
+    fn word_fallback_kind(token: &str, char_map: &HashMap<String, String>) -> SegmentKind {
+        if token.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+            return SegmentKind::Passthrough;
+        }
+
+        if token.chars().count() == 1 {
+            return if char_map.contains_key(token) {
+                SegmentKind::Char
+            } else {
+                SegmentKind::Passthrough
+            };
+        }
+
+        if is_han_phrase(token) {
+            SegmentKind::Char
+        } else {
+            SegmentKind::Passthrough
+        }
+    }
+
+    fn lookup_word<'a>(
+        word_map: &'a HashMap<String, String>,
+        word: &str,
+        variant_fallback: bool,
+    ) -> Option<&'a String> {
+        if let Some(mapped) = word_map.get(word) {
+            return Some(mapped);
+        }
+        if !variant_fallback {
+            return None;
+        }
+
+        let variants = variant_dictionary();
+        [variants.to_simplified(word), variants.to_traditional(word)]
+            .iter()
+            .filter(|converted| converted.as_str() != word)
+            .find_map(|converted| word_map.get(converted))
+    }
+
+    fn segment_word_tokens_with_maps(
+        tokens: &[String],
         char_map: &HashMap<String, String>,
         word_map: &HashMap<String, String>,
         max_word_len: usize,
-    ) -> String {
-        tokens.retain(|token| !token.is_empty());
-        if tokens.is_empty() {
-            return String::new();
-        }
-
-        let mut out = Vec::with_capacity(tokens.len());
+        variant_fallback: bool,
+    ) -> Vec<WordSegment> {
+        let mut segments = Vec::with_capacity(tokens.len());
         let mut idx = 0usize;
 
         while idx < tokens.len() {
-            if let Some(mapped) = word_map.get(&tokens[idx]) {
-                out.push(romanize_pinyin_phrase(mapped));
+            if let Some(mapped) = lookup_word(word_map, &tokens[idx], variant_fallback) {
+                segments.push(WordSegment {
+                    start: idx,
+                    end: idx + 1,
+                    romanized: romanize_pinyin_phrase(mapped),
+                    kind: SegmentKind::Word,
+                });
                 idx += 1;
                 continue;
             }
@@ -893,24 +1563,93 @@ mod extension {
                     let span = end - idx + 1;
 
                     if span >= 2 {
-                        if let Some(mapped) = word_map.get(&candidate) {
+                        if let Some(mapped) = lookup_word(word_map, &candidate, variant_fallback) {
                             best = Some((span, romanize_pinyin_phrase(mapped)));
                         }
                     }
                 }
 
                 if let Some((span, mapped)) = best {
-                    out.push(mapped);
+                    segments.push(WordSegment {
+                        start: idx,
+                        end: idx + span,
+                        romanized: mapped,
+                        kind: SegmentKind::Word,
+                    });
                     idx += span;
                     continue;
                 }
             }
 
-            out.push(map_word_fallback(&tokens[idx], char_map));
+            segments.push(WordSegment {
+                start: idx,
+                end: idx + 1,
+                romanized: map_word_fallback(&tokens[idx], char_map),
+                kind: word_fallback_kind(&tokens[idx], char_map),
+            });
             idx += 1;
         }
 
-        out.join(" ")
+        segments
+    }
+
+    // Under the separator policy punctuation reaches segmentation only to stop
+    // word matches from spanning it; it is removed afterwards.
+    fn is_dropped_punctuation_segment(tokens: &[String], options: &RomanizeOptions) -> bool {
+        !options.punctuation.emits_token()
+            && tokens
+                .iter()
+                .all(|token| script::is_punctuation_text(token))
+    }
+
+    fn romanize_word_tokens_with_maps(
+        tokens: Vec<String>,
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        max_word_len: usize,
+        options: &RomanizeOptions,
+    ) -> String {
+        let units =
+            romanize_word_units_with_maps(tokens, char_map, word_map, max_word_len, options);
+        join_romanized_units(&units, options)
+    }
+
+    // One romanized unit per word segment, before separator and casing.
+    fn romanize_word_units_with_maps(
+        mut tokens: Vec<String>,
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        max_word_len: usize,
+        options: &RomanizeOptions,
+    ) -> Vec<String> {
+        tokens.retain(|token| {
+            !token.is_empty()
+                && (options.punctuation.keeps_token() || !script::is_punctuation_text(token))
+        });
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let segments = segment_word_tokens_with_maps(
+            &tokens,
+            char_map,
+            word_map,
+            max_word_len,
+            options.variant_fallback,
+        );
+        let mut out = Vec::with_capacity(segments.len());
+        for segment in segments {
+            if is_dropped_punctuation_segment(&tokens[segment.start..segment.end], options) {
+                continue;
+            }
+            let from_dictionary = segment.kind != SegmentKind::Passthrough;
+            out.push(apply_output_style(
+                segment.romanized,
+                from_dictionary,
+                options,
+            ));
+        }
+        out
     }
 
     fn romanize_word_tokens(mut tokens: Vec<String>) -> String {
@@ -925,6 +1664,7 @@ mod extension {
                 &cache.char_map,
                 &cache.word_map,
                 cache.max_word_len,
+                &RomanizeOptions::default(),
             )
         })
     }
@@ -934,31 +1674,555 @@ mod extension {
         char_map: &HashMap<String, String>,
         word_map: &HashMap<String, String>,
         max_word_len: usize,
+        options: &RomanizeOptions,
     ) -> String {
-        let romanized_text = romanize_plain_text_with_char_map(origin, char_map);
+        let romanized_text = romanize_plain_text_with_char_map(origin, char_map, options);
         let tokens = tokenize_plain(&romanized_text);
-        romanize_word_tokens_with_maps(tokens, char_map, word_map, max_word_len)
+        romanize_word_tokens_with_maps(tokens, char_map, word_map, max_word_len, options)
     }
 
-    fn pinyin_word_romanize_impl(origin: &str) -> String {
-        with_dictionary_cache(|cache| {
-            pinyin_word_romanize_with_maps(
-                origin,
-                &cache.char_map,
-                &cache.word_map,
-                cache.max_word_len,
-            )
+    // The longest surname-table entry at the start of the name takes its
+    // surname reading; the rest of the name is romanized in word mode. The
+    // name is normalized first, and `variant_fallback` lets a Traditional
+    // surname match a Simplified entry (and the reverse).
+    fn romanize_name_with_maps(
+        origin: &str,
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        max_word_len: usize,
+        surnames: &SurnameCacheEntry,
+        options: &RomanizeOptions,
+    ) -> String {
+        let normalized: String = normalized_chars(origin.trim_start(), options)
+            .into_iter()
+            .map(|(_, _, ch)| ch)
+            .collect();
+        let name = normalized.as_str();
+        let boundaries: Vec<usize> = name
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .skip(1)
+            .chain(std::iter::once(name.len()))
+            .collect();
+        let surname = (1..=usize::min(surnames.max_len, boundaries.len()))
+            .rev()
+            .find_map(|len| {
+                let end = boundaries[len - 1];
+                lookup_word(&surnames.surnames, &name[..end], options.variant_fallback)
+                    .map(|reading| (end, reading))
+            });
+        let Some((end, reading)) = surname else {
+            return pinyin_word_romanize_with_maps(
+                origin,
+                char_map,
+                word_map,
+                max_word_len,
+                options,
+            );
+        };
+
+        let mut units = vec![apply_output_style(
+            romanize_pinyin_phrase(reading),
+            true,
+            options,
+        )];
+        let romanized_text = romanize_plain_text_with_char_map(&name[end..], char_map, options);
+        units.extend(romanize_word_units_with_maps(
+            tokenize_plain(&romanized_text),
+            char_map,
+            word_map,
+            max_word_len,
+            options,
+        ));
+        join_romanized_units(&units, options)
+    }
+
+    fn pinyin_name_romanize_impl(
+        origin: &str,
+        suffix: Option<&str>,
+        options: &RomanizeOptions,
+    ) -> String {
+        let canonical_suffix = suffix.and_then(canonicalize_table_suffix);
+        with_surname_cache(
+            canonical_suffix.as_deref(),
+            |surnames| match canonical_suffix.as_deref() {
+                None => with_dictionary_cache(|cache| {
+                    romanize_name_with_maps(
+                        origin,
+                        &cache.char_map,
+                        &cache.word_map,
+                        cache.max_word_len,
+                        surnames,
+                        options,
+                    )
+                }),
+                Some(canonical_suffix) => with_suffix_word_cache(canonical_suffix, |entry| {
+                    romanize_name_with_maps(
+                        origin,
+                        &entry.char_map,
+                        &entry.word_map,
+                        entry.max_word_len,
+                        surnames,
+                        options,
+                    )
+                }),
+            },
+        )
+    }
+
+    struct PlainToken {
+        value: String,
+        byte_start: usize,
+        byte_end: usize,
+        char_start: usize,
+        char_end: usize,
+    }
+
+    struct RomanizedToken {
+        source: String,
+        byte_start: usize,
+        byte_end: usize,
+        char_start: usize,
+        char_end: usize,
+        pinyin: String,
+        kind: SegmentKind,
+    }
+
+    fn plain_tokens_with_offsets(
+        origin: &str,
+        char_map: &HashMap<String, String>,
+        options: &RomanizeOptions,
+    ) -> Vec<PlainToken> {
+        let boundaries: Vec<usize> = origin.char_indices().map(|(start, _)| start).collect();
+        let char_index = |byte: usize| boundaries.partition_point(|&start| start < byte);
+        let mut tokens = Vec::new();
+
+        for piece in split_input(origin, options) {
+            let value = match piece.kind {
+                PieceKind::AsciiRun => Some(piece.value.to_ascii_lowercase()),
+                PieceKind::Space => None,
+                PieceKind::Punctuation => options
+                    .punctuation
+                    .keeps_token()
+                    .then(|| piece.value.clone()),
+                PieceKind::Other => char_map
+                    .contains_key(&piece.value)
+                    .then(|| piece.value.clone()),
+            };
+
+            if let Some(value) = value {
+                tokens.push(PlainToken {
+                    value,
+                    byte_start: piece.start,
+                    byte_end: piece.end,
+                    char_start: char_index(piece.start),
+                    char_end: char_index(piece.end),
+                });
+            }
+        }
+
+        tokens
+    }
+
+    fn romanize_tokens_with_maps(
+        origin: &str,
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        max_word_len: usize,
+        options: &RomanizeOptions,
+    ) -> Vec<RomanizedToken> {
+        let plain = plain_tokens_with_offsets(origin, char_map, options);
+        let values: Vec<String> = plain.iter().map(|token| token.value.clone()).collect();
+
+        segment_word_tokens_with_maps(
+            &values,
+            char_map,
+            word_map,
+            max_word_len,
+            options.variant_fallback,
+        )
+        .into_iter()
+        .filter(|segment| {
+            !is_dropped_punctuation_segment(&values[segment.start..segment.end], options)
+        })
+        .map(|segment| {
+            let first = &plain[segment.start];
+            let last = &plain[segment.end - 1];
+            RomanizedToken {
+                source: origin[first.byte_start..last.byte_end].to_string(),
+                byte_start: first.byte_start,
+                byte_end: last.byte_end,
+                char_start: first.char_start,
+                char_end: last.char_end,
+                pinyin: apply_output_style(
+                    segment.romanized,
+                    segment.kind != SegmentKind::Passthrough,
+                    options,
+                ),
+                kind: segment.kind,
+            }
+        })
+        .collect()
+    }
+
+    fn pinyin_romanize_tokens_impl(
+        origin: &str,
+        suffix: Option<&str>,
+        options: &RomanizeOptions,
+    ) -> Vec<RomanizedToken> {
+        match suffix.and_then(canonicalize_table_suffix) {
+            Some(canonical_suffix) => with_suffix_word_cache(&canonical_suffix, |entry| {
+                romanize_tokens_with_maps(
+                    origin,
+                    &entry.char_map,
+                    &entry.word_map,
+                    entry.max_word_len,
+                    options,
+                )
+            }),
+            None => with_dictionary_cache(|cache| {
+                romanize_tokens_with_maps(
+                    origin,
+                    &cache.char_map,
+                    &cache.word_map,
+                    cache.max_word_len,
+                    options,
+                )
+            }),
+        }
+    }
+
+    struct FirstLetterOptions {
+        ascii_letters: bool,
+        digits: bool,
+        other: String,
+    }
+
+    impl Default for FirstLetterOptions {
+        fn default() -> Self {
+            Self {
+                ascii_letters: true,
+                digits: false,
+                other: "#".to_string(),
+            }
+        }
+    }
+
+    fn parse_first_letter_options(options: &pgrx::JsonB) -> FirstLetterOptions {
+        let serde_json::Value::Object(entries) = &options.0 else {
+            error!("first letter options must be a jsonb object");
+        };
+
+        let mut parsed = FirstLetterOptions::default();
+        for (key, value) in entries {
+            let serde_json::Value::String(text) = value else {
+                error!("first letter option \"{key}\" must be a string");
+            };
+            match key.as_str() {
+                "ascii" => {
+                    parsed.ascii_letters = match text.trim().to_ascii_lowercase().as_str() {
+                        "letter" => true,
+                        "other" => false,
+                        _ => error!("first letter option \"ascii\" must be one of letter, other"),
+                    };
+                }
+                "digits" => {
+                    parsed.digits = match text.trim().to_ascii_lowercase().as_str() {
+                        "keep" => true,
+                        "other" => false,
+                        _ => error!("first letter option \"digits\" must be one of keep, other"),
+                    };
+                }
+                "other" => {
+                    parsed.other = text.clone();
+                }
+                _ => error!("unrecognized first letter option \"{key}\""),
+            }
+        }
+        parsed
+    }
+
+    // The first token goes through the same word segmentation as
+    // `pinyin_word_romanize`, so single-character word entries (`曾` read as
+    // `zeng`) override the character reading. Leading whitespace and
+    // punctuation are skipped; any other unmapped leading character counts as
+    // `other`.
+    fn pinyin_first_letter_impl(
+        origin: &str,
+        suffix: Option<&str>,
+        options: &FirstLetterOptions,
+    ) -> String {
+        let tokens = pinyin_romanize_tokens_impl(origin, suffix, &RomanizeOptions::default());
+        let Some(first) = tokens.first() else {
+            return options.other.clone();
+        };
+        let skipped_unknown = origin[..first.byte_start]
+            .chars()
+            .any(|ch| !ch.is_whitespace() && !script::is_punctuation(ch));
+        if skipped_unknown {
+            return options.other.clone();
+        }
+
+        match first.pinyin.chars().next() {
+            Some(ch) if ch.is_ascii_digit() && options.digits => ch.to_string(),
+            Some(ch) if ch.is_ascii_alphabetic() => {
+                if first.kind == SegmentKind::Passthrough && !options.ascii_letters {
+                    options.other.clone()
+                } else {
+                    ch.to_ascii_uppercase().to_string()
+                }
+            }
+            _ => options.other.clone(),
+        }
+    }
+
+    fn romanized_syllables(origin: &str) -> Vec<String> {
+        pinyin_word_romanize_impl(origin)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect()
+    }
+
+    // Text with Han characters is looked up by its word romanization; pinyin
+    // is used as it is, split into syllables when written without spaces.
+    fn reading_query_key(
+        origin: &str,
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        max_word_len: usize,
+    ) -> String {
+        if origin.chars().any(script::is_han_char) {
+            let romanized = pinyin_word_romanize_with_maps(
+                origin,
+                char_map,
+                word_map,
+                max_word_len,
+                &RomanizeOptions::default(),
+            );
+            return reading_index::reading_key(&romanized);
+        }
+
+        origin
+            .split_whitespace()
+            .flat_map(|part| {
+                let key = reading_index::reading_key(part);
+                regex_phrase::pinyin_query_tokens(&key, regex_token_dictionary())
+                    .unwrap_or_else(|| vec![key])
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Built on first use, so plain romanization never pays for it.
+    fn reading_index<'a>(
+        index: &'a OnceLock<ReadingIndex>,
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        canonical_suffix: Option<&str>,
+    ) -> &'a ReadingIndex {
+        index.get_or_init(|| {
+            let overlay = overlay_table_name("pinyin_word_frequency", canonical_suffix);
+            let frequencies = fetch_word_frequencies(overlay.as_deref());
+            ReadingIndex::new(char_map, word_map, &frequencies)
+        })
+    }
+
+    // Runs `f` with the reading index and the reading key of `origin`.
+    fn with_reading_query<R>(
+        origin: &str,
+        suffix: Option<&str>,
+        f: impl FnOnce(&ReadingIndex, &str) -> R,
+    ) -> R {
+        match suffix.and_then(canonicalize_table_suffix) {
+            None => with_dictionary_cache(|cache| {
+                let index =
+                    reading_index(&cache.reading_index, &cache.char_map, &cache.word_map, None);
+                let key =
+                    reading_query_key(origin, &cache.char_map, &cache.word_map, cache.max_word_len);
+                f(index, &key)
+            }),
+            Some(canonical_suffix) => with_suffix_word_cache(&canonical_suffix, |entry| {
+                let index = reading_index(
+                    &entry.reading_index,
+                    &entry.char_map,
+                    &entry.word_map,
+                    Some(&canonical_suffix),
+                );
+                let key =
+                    reading_query_key(origin, &entry.char_map, &entry.word_map, entry.max_word_len);
+                f(index, &key)
+            }),
+        }
+    }
+
+    fn pinyin_homophones_impl(origin: &str, suffix: Option<&str>) -> Vec<(String, String)> {
+        with_reading_query(origin, suffix, |index, key| {
+            let chars = index
+                .chars(key)
+                .iter()
+                .map(|term| (term, SegmentKind::Char));
+            let words = index
+                .words(key)
+                .iter()
+                .map(|term| (term, SegmentKind::Word));
+
+            chars
+                .chain(words)
+                .filter(|(term, _)| term.as_str() != origin.trim())
+                .map(|(term, kind)| (term.clone(), kind.as_str().to_string()))
+                .collect()
+        })
+    }
+
+    fn pinyin_to_hanzi_impl(origin: &str, limit: i32, suffix: Option<&str>) -> Vec<(i32, String)> {
+        if limit <= 0 {
+            error!("pinyin_to_hanzi limit must be positive");
+        }
+        with_reading_query(origin, suffix, |index, key| {
+            let syllables: Vec<String> = key.split_whitespace().map(str::to_string).collect();
+            index
+                .candidates(&syllables, limit as usize)
+                .into_iter()
+                .enumerate()
+                .map(|(idx, candidate)| ((idx + 1) as i32, candidate))
+                .collect()
         })
     }
 
+    fn pinyin_sort_key_impl(origin: &str, suffix: Option<&str>) -> Vec<u8> {
+        let tokens = pinyin_romanize_tokens_impl(origin, suffix, &RomanizeOptions::default());
+        sort_key::sort_key(
+            tokens.iter().map(|token| {
+                (
+                    token.pinyin.as_str(),
+                    token.kind != SegmentKind::Passthrough,
+                )
+            }),
+            origin,
+        )
+    }
+
+    fn syllable_byte_ranges(
+        origin: &str,
+        tokens: &[RomanizedToken],
+    ) -> Vec<(String, usize, usize)> {
+        let mut out = Vec::new();
+        for token in tokens {
+            let syllables: Vec<&str> = token.pinyin.split_whitespace().collect();
+            let chars: Vec<(usize, char)> = origin[token.byte_start..token.byte_end]
+                .char_indices()
+                .collect();
+
+            if token.kind != SegmentKind::Passthrough && syllables.len() == chars.len() {
+                for (syllable, (offset, ch)) in syllables.iter().zip(chars) {
+                    let start = token.byte_start + offset;
+                    out.push((syllable.to_string(), start, start + ch.len_utf8()));
+                }
+            } else {
+                for syllable in syllables {
+                    out.push((syllable.to_string(), token.byte_start, token.byte_end));
+                }
+            }
+        }
+        out
+    }
+
+    fn pinyin_highlight_impl(
+        origin: &str,
+        query: &str,
+        start_tag: &str,
+        end_tag: &str,
+        suffix: Option<&str>,
+    ) -> String {
+        let Some(query_tokens) = regex_phrase::pinyin_query_tokens(query, regex_token_dictionary())
+        else {
+            return origin.to_string();
+        };
+
+        let tokens = pinyin_romanize_tokens_impl(origin, suffix, &RomanizeOptions::default());
+        let syllables = syllable_byte_ranges(origin, &tokens);
+        let names: Vec<&str> = syllables
+            .iter()
+            .map(|(syllable, _, _)| syllable.as_str())
+            .collect();
+        let matched = highlight::match_phrase_syllables(&names, &query_tokens);
+
+        let ranges: Vec<(usize, usize)> = syllables
+            .iter()
+            .zip(matched)
+            .filter(|(_, hit)| *hit)
+            .map(|((_, start, end), _)| (*start, *end))
+            .collect();
+        highlight::wrap_byte_ranges(origin, &ranges, start_tag, end_tag)
+    }
+
+    type RomanizedTokenRow = (i32, String, i32, i32, i32, i32, String, String);
+
+    fn romanized_token_rows(tokens: Vec<RomanizedToken>) -> Vec<RomanizedTokenRow> {
+        tokens
+            .into_iter()
+            .enumerate()
+            .map(|(idx, token)| {
+                (
+                    (idx + 1) as i32,
+                    token.source,
+                    token.char_start as i32,
+                    token.char_end as i32,
+                    token.byte_start as i32,
+                    token.byte_end as i32,
+                    token.pinyin,
+                    token.kind.as_str().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn pinyin_word_romanize_array_impl(origin: &str, suffix: Option<&str>) -> Vec<String> {
+        pinyin_romanize_tokens_impl(origin, suffix, &RomanizeOptions::default())
+            .into_iter()
+            .map(|token| token.pinyin)
+            .collect()
+    }
+
+    fn pinyin_romanize_jsonb_impl(origin: &str, suffix: Option<&str>) -> pgrx::JsonB {
+        let tokens = pinyin_romanize_tokens_impl(origin, suffix, &RomanizeOptions::default())
+            .into_iter()
+            .map(|token| {
+                serde_json::json!({
+                    "source": token.source,
+                    "pinyin": token.pinyin,
+                    "kind": token.kind.as_str(),
+                    "char_start": token.char_start,
+                    "char_end": token.char_end,
+                    "byte_start": token.byte_start,
+                    "byte_end": token.byte_end,
+                })
+            })
+            .collect();
+        pgrx::JsonB(serde_json::Value::Array(tokens))
+    }
+
+    fn pinyin_word_romanize_impl(origin: &str) -> String {
+        pinyin_word_romanize_with_options_impl(origin, None, &RomanizeOptions::default())
+    }
+
     fn pinyin_word_romanize_with_suffix_impl(origin: &str, suffix: &str) -> String {
-        match canonicalize_table_suffix(suffix) {
+        pinyin_word_romanize_with_options_impl(origin, Some(suffix), &RomanizeOptions::default())
+    }
+
+    fn pinyin_word_romanize_with_options_impl(
+        origin: &str,
+        suffix: Option<&str>,
+        options: &RomanizeOptions,
+    ) -> String {
+        match suffix.and_then(canonicalize_table_suffix) {
             Some(canonical_suffix) => with_suffix_word_cache(&canonical_suffix, |entry| {
                 pinyin_word_romanize_with_maps(
                     origin,
                     &entry.char_map,
                     &entry.word_map,
                     entry.max_word_len,
+                    options,
                 )
             }),
             None => with_dictionary_cache(|cache| {
@@ -967,13 +2231,99 @@ mod extension {
                     &cache.char_map,
                     &cache.word_map,
                     cache.max_word_len,
+                    options,
                 )
             }),
         }
     }
 
+    // One cache lookup for the whole array; NULL elements stay NULL.
+    fn pinyin_word_romanize_batch_impl(
+        origins: Array<'_, &str>,
+        suffix: Option<&str>,
+        options: &RomanizeOptions,
+    ) -> Vec<Option<String>> {
+        let romanize_all = |char_map: &HashMap<String, String>,
+                            word_map: &HashMap<String, String>,
+                            max_word_len: usize|
+         -> Vec<Option<String>> {
+            origins
+                .iter()
+                .map(|origin| {
+                    Some(pinyin_word_romanize_with_maps(
+                        origin?,
+                        char_map,
+                        word_map,
+                        max_word_len,
+                        options,
+                    ))
+                })
+                .collect()
+        };
+
+        match suffix.and_then(canonicalize_table_suffix) {
+            Some(canonical_suffix) => with_suffix_word_cache(&canonical_suffix, |entry| {
+                romanize_all(&entry.char_map, &entry.word_map, entry.max_word_len)
+            }),
+            None => with_dictionary_cache(|cache| {
+                romanize_all(&cache.char_map, &cache.word_map, cache.max_word_len)
+            }),
+        }
+    }
+
+    // Everything outside `[a-z0-9]` becomes a hyphen, and runs of hyphens
+    // collapse into one.
+    fn slugify_romanized(romanized: &str) -> String {
+        let mut slug = String::with_capacity(romanized.len());
+        for ch in romanized.chars().flat_map(char::to_lowercase) {
+            if ch.is_ascii_lowercase() || ch.is_ascii_digit() {
+                slug.push(ch);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.truncate(slug.trim_end_matches('-').len());
+        slug
+    }
+
+    // Cuts at the last hyphen that fits, so no syllable is split unless the
+    // first one is already longer than `max_len`.
+    fn truncate_slug(slug: &str, max_len: usize) -> &str {
+        if slug.len() <= max_len {
+            return slug;
+        }
+        match slug[..=max_len].rfind('-') {
+            Some(end) if end > 0 => &slug[..end],
+            _ => &slug[..max_len],
+        }
+    }
+
+    fn pinyin_slugify_impl(origin: &str, max_len: Option<i32>, suffix: Option<&str>) -> String {
+        let max_len = max_len.map(|max_len| {
+            usize::try_from(max_len)
+                .ok()
+                .filter(|max_len| *max_len > 0)
+                .unwrap_or_else(|| error!("slug max_len must be positive"))
+        });
+        // Slugs are new output, so they can normalize full-width input and fold
+        // accents without changing any stored romanization.
+        let options = RomanizeOptions {
+            normalize: Normalization::Nfkc,
+            fold_latin: true,
+            separator: "-".to_string(),
+            ..RomanizeOptions::default()
+        };
+        let romanized = pinyin_word_romanize_with_options_impl(origin, suffix, &options);
+        let slug = slugify_romanized(&romanized);
+        match max_len {
+            Some(max_len) => truncate_slug(&slug, max_len).to_string(),
+            None => slug,
+        }
+    }
+
     fn pinyin_word_romanize_tokenizer_impl(tokenizer_input: AnyElement) -> String {
-        if let Some(tokens) = fetch_tokenizer_input_tokens(tokenizer_input) {
+        if let Some(mut tokens) = fetch_tokenizer_input_tokens(tokenizer_input) {
+            normalize_tokens(&mut tokens, &RomanizeOptions::default());
             return romanize_word_tokens(tokens);
         }
 
@@ -989,7 +2339,8 @@ mod extension {
     ) -> String {
         let canonical_suffix = canonicalize_table_suffix(suffix);
 
-        if let Some(tokens) = fetch_tokenizer_input_tokens(tokenizer_input) {
+        if let Some(mut tokens) = fetch_tokenizer_input_tokens(tokenizer_input) {
+            normalize_tokens(&mut tokens, &RomanizeOptions::default());
             return match canonical_suffix.as_deref() {
                 Some(canonical_suffix) => with_suffix_word_cache(canonical_suffix, |entry| {
                     romanize_word_tokens_with_maps(
@@ -997,6 +2348,7 @@ mod extension {
                         &entry.char_map,
                         &entry.word_map,
                         entry.max_word_len,
+                        &RomanizeOptions::default(),
                     )
                 }),
                 None => with_dictionary_cache(|cache| {
@@ -1005,6 +2357,7 @@ mod extension {
                         &cache.char_map,
                         &cache.word_map,
                         cache.max_word_len,
+                        &RomanizeOptions::default(),
                     )
                 }),
             };
@@ -1018,6 +2371,7 @@ mod extension {
                         &entry.char_map,
                         &entry.word_map,
                         entry.max_word_len,
+                        &RomanizeOptions::default(),
                     )
                 }),
                 None => with_dictionary_cache(|cache| {
@@ -1026,6 +2380,7 @@ mod extension {
                         &cache.char_map,
                         &cache.word_map,
                         cache.max_word_len,
+                        &RomanizeOptions::default(),
                     )
                 }),
             },
@@ -1033,489 +2388,2846 @@ mod extension {
         }
     }
 
-    #[pg_extern(immutable, strict, parallel_safe)]
-    fn pinyin_char_romanize(origin: &str) -> String {
-        pinyin_char_romanize_impl(origin)
-    }
-
-    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize")]
-    fn pinyin_char_romanize_with_suffix(origin: &str, suffix: &str) -> String {
-        pinyin_char_romanize_with_suffix_impl(origin, suffix)
-    }
+    const TS_TOKEN_ASCII_WORD: c_int = 1;
+    const TS_TOKEN_HAN: c_int = 2;
+    // Same id as the default parser's `blank`, so `prsd_headline` treats it as a space.
+    const TS_TOKEN_BLANK: c_int = 12;
 
-    #[pg_extern(immutable, strict, parallel_safe)]
-    fn pinyin_word_romanize(origin: &str) -> String {
-        pinyin_word_romanize_impl(origin)
+    struct TextSearchParserState {
+        buffer: *mut c_char,
+        tokens: Vec<(usize, usize, c_int)>,
+        next: usize,
     }
 
-    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize")]
-    fn pinyin_word_romanize_with_suffix(origin: &str, suffix: &str) -> String {
-        pinyin_word_romanize_with_suffix_impl(origin, suffix)
+    struct TextSearchDictionary {
+        canonical_suffix: Option<String>,
+        initials: bool,
     }
 
-    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize")]
-    fn pinyin_word_romanize_with_tokenizer(tokenizer_input: AnyElement) -> String {
-        pinyin_word_romanize_tokenizer_impl(tokenizer_input)
+    struct TextSearchLexeme {
+        nvariant: u16,
+        flags: u16,
+        value: String,
     }
 
-    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize")]
-    fn pinyin_word_romanize_with_tokenizer_and_suffix(
-        tokenizer_input: AnyElement,
-        suffix: &str,
-    ) -> String {
-        pinyin_word_romanize_tokenizer_with_suffix_impl(tokenizer_input, suffix)
+    unsafe fn text_search_buffer_str<'a>(buffer: *const c_char, len: usize) -> &'a str {
+        if buffer.is_null() || len == 0 {
+            return "";
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(buffer.cast::<u8>(), len) };
+        match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(err) => error!("pinyin text search input is not valid UTF-8: {err}"),
+        }
     }
 
-    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_regex_phrase_patterns")]
-    fn pinyin_regex_phrase_patterns_default(value: &str) -> Option<Vec<String>> {
-        pinyin_regex_phrase_patterns_impl(value, false)
+    fn text_search_token_type(ch: char) -> c_int {
+        if ch.is_ascii_alphanumeric() {
+            TS_TOKEN_ASCII_WORD
+        } else if ch.is_whitespace() || script::is_punctuation(ch) || ch.is_control() {
+            TS_TOKEN_BLANK
+        } else {
+            TS_TOKEN_HAN
+        }
     }
 
-    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_regex_phrase_patterns")]
-    fn pinyin_regex_phrase_patterns_with_generated(
-        value: &str,
-        generated_pinyin: bool,
-    ) -> Option<Vec<String>> {
-        pinyin_regex_phrase_patterns_impl(value, generated_pinyin)
+    fn split_text_search_tokens(input: &str) -> Vec<(usize, usize, c_int)> {
+        let mut tokens: Vec<(usize, usize, c_int)> = Vec::new();
+        for (start, ch) in input.char_indices() {
+            let token_type = text_search_token_type(ch);
+            let end = start + ch.len_utf8();
+            match tokens.last_mut() {
+                Some(last) if last.2 == token_type => last.1 = end,
+                _ => tokens.push((start, end, token_type)),
+            }
+        }
+        tokens
     }
 
-    #[pg_extern(volatile, parallel_unsafe, name = "pinyin_clear_suffix_cache")]
-    fn pinyin_clear_suffix_cache_all() -> i64 {
-        clear_all_suffix_cache_impl()
+    fn han_run_lexemes(
+        run: &str,
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        max_word_len: usize,
+        initials: bool,
+    ) -> Vec<TextSearchLexeme> {
+        let romanized_text =
+            romanize_plain_text_with_char_map(run, char_map, &RomanizeOptions::default());
+        let tokens = tokenize_plain(&romanized_text);
+        let segments =
+            segment_word_tokens_with_maps(&tokens, char_map, word_map, max_word_len, false);
+
+        let mut lexemes = Vec::with_capacity(segments.len() * 3);
+        for (segment_idx, segment) in segments.iter().enumerate() {
+            let syllables: Vec<&str> = segment.romanized.split_whitespace().collect();
+            let mut variants = vec![
+                tokens[segment.start..segment.end].concat(),
+                syllables.concat(),
+            ];
+            if initials {
+                variants.push(
+                    syllables
+                        .iter()
+                        .filter_map(|syllable| syllable.chars().next())
+                        .collect(),
+                );
+            }
+
+            let mut first = true;
+            for (variant_idx, value) in variants.iter().enumerate() {
+                if value.is_empty() || variants[..variant_idx].contains(value) {
+                    continue;
+                }
+                lexemes.push(TextSearchLexeme {
+                    nvariant: (variant_idx + 1) as u16,
+                    flags: if first && segment_idx > 0 {
+                        pg_sys::TSL_ADDPOS as u16
+                    } else {
+                        0
+                    },
+                    value: value.clone(),
+                });
+                first = false;
+            }
+        }
+        lexemes
     }
 
-    #[pg_extern(volatile, strict, parallel_unsafe, name = "pinyin_clear_suffix_cache")]
-    fn pinyin_clear_suffix_cache_for_suffix(suffix: &str) -> bool {
-        clear_suffix_cache_impl(suffix)
+    fn text_search_lexize_impl(
+        token: &str,
+        dictionary: &TextSearchDictionary,
+    ) -> Vec<TextSearchLexeme> {
+        if token.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+            return vec![TextSearchLexeme {
+                nvariant: 0,
+                flags: 0,
+                value: token.to_ascii_lowercase(),
+            }];
+        }
+
+        match dictionary.canonical_suffix.as_deref() {
+            Some(canonical_suffix) => with_suffix_word_cache(canonical_suffix, |entry| {
+                han_run_lexemes(
+                    token,
+                    &entry.char_map,
+                    &entry.word_map,
+                    entry.max_word_len,
+                    dictionary.initials,
+                )
+            }),
+            None => with_dictionary_cache(|cache| {
+                han_run_lexemes(
+                    token,
+                    &cache.char_map,
+                    &cache.word_map,
+                    cache.max_word_len,
+                    dictionary.initials,
+                )
+            }),
+        }
+    }
+
+    fn palloc_ts_lexemes(lexemes: &[TextSearchLexeme]) -> *mut pg_sys::TSLexeme {
+        unsafe {
+            let out = pg_sys::palloc0(mem::size_of::<pg_sys::TSLexeme>() * (lexemes.len() + 1))
+                .cast::<pg_sys::TSLexeme>();
+            for (idx, lexeme) in lexemes.iter().enumerate() {
+                let entry = &mut *out.add(idx);
+                entry.nvariant = lexeme.nvariant;
+                entry.flags = lexeme.flags;
+                entry.lexeme = pg_sys::pnstrdup(lexeme.value.as_ptr().cast(), lexeme.value.len());
+            }
+            out
+        }
+    }
+
+    fn palloc_ts_lextypes(types: &[(c_int, &str, &str)]) -> *mut pg_sys::LexDescr {
+        unsafe {
+            let out = pg_sys::palloc0(mem::size_of::<pg_sys::LexDescr>() * (types.len() + 1))
+                .cast::<pg_sys::LexDescr>();
+            for (idx, (lexid, alias, descr)) in types.iter().enumerate() {
+                let entry = &mut *out.add(idx);
+                entry.lexid = *lexid;
+                entry.alias = pg_sys::pnstrdup(alias.as_ptr().cast(), alias.len());
+                entry.descr = pg_sys::pnstrdup(descr.as_ptr().cast(), descr.len());
+            }
+            out
+        }
+    }
+
+    fn internal_ptr<T>(value: Internal) -> *mut T {
+        value
+            .unwrap()
+            .map(|datum| datum.cast_mut_ptr::<T>())
+            .unwrap_or(ptr::null_mut())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_char_romanize(origin: &str) -> String {
+        pinyin_char_romanize_impl(origin)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize")]
+    fn pinyin_char_romanize_with_suffix(origin: &str, suffix: &str) -> String {
+        pinyin_char_romanize_with_suffix_impl(origin, suffix)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize")]
+    fn pinyin_char_romanize_with_options(
+        origin: &str,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> String {
+        let options = parse_romanize_options(&options);
+        let parts = pinyin_char_romanize_array_impl(origin, Some(suffix), &options);
+        join_romanized_units(&parts, &options)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize")]
+    fn pinyin_char_romanize_elements(origins: Array<'_, &str>) -> Vec<Option<String>> {
+        pinyin_char_romanize_batch_impl(origins, None, &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize")]
+    fn pinyin_char_romanize_elements_with_suffix(
+        origins: Array<'_, &str>,
+        suffix: &str,
+    ) -> Vec<Option<String>> {
+        pinyin_char_romanize_batch_impl(origins, Some(suffix), &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize")]
+    fn pinyin_char_romanize_elements_with_options(
+        origins: Array<'_, &str>,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> Vec<Option<String>> {
+        let options = parse_romanize_options(&options);
+        pinyin_char_romanize_batch_impl(origins, Some(suffix), &options)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_char_romanize_batch(origins: Array<'_, &str>) -> Vec<Option<String>> {
+        pinyin_char_romanize_batch_impl(origins, None, &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize_batch")]
+    fn pinyin_char_romanize_batch_with_suffix(
+        origins: Array<'_, &str>,
+        suffix: &str,
+    ) -> Vec<Option<String>> {
+        pinyin_char_romanize_batch_impl(origins, Some(suffix), &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize_batch")]
+    fn pinyin_char_romanize_batch_with_options(
+        origins: Array<'_, &str>,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> Vec<Option<String>> {
+        let options = parse_romanize_options(&options);
+        pinyin_char_romanize_batch_impl(origins, Some(suffix), &options)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_word_romanize(origin: &str) -> String {
+        pinyin_word_romanize_impl(origin)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize")]
+    fn pinyin_word_romanize_with_suffix(origin: &str, suffix: &str) -> String {
+        pinyin_word_romanize_with_suffix_impl(origin, suffix)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize")]
+    fn pinyin_word_romanize_with_options(
+        origin: &str,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> String {
+        let options = parse_romanize_options(&options);
+        pinyin_word_romanize_with_options_impl(origin, Some(suffix), &options)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_word_romanize_batch(origins: Array<'_, &str>) -> Vec<Option<String>> {
+        pinyin_word_romanize_batch_impl(origins, None, &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize_batch")]
+    fn pinyin_word_romanize_batch_with_suffix(
+        origins: Array<'_, &str>,
+        suffix: &str,
+    ) -> Vec<Option<String>> {
+        pinyin_word_romanize_batch_impl(origins, Some(suffix), &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize_batch")]
+    fn pinyin_word_romanize_batch_with_options(
+        origins: Array<'_, &str>,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> Vec<Option<String>> {
+        let options = parse_romanize_options(&options);
+        pinyin_word_romanize_batch_impl(origins, Some(suffix), &options)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize")]
+    fn pinyin_word_romanize_with_tokenizer(tokenizer_input: AnyElement) -> String {
+        pinyin_word_romanize_tokenizer_impl(tokenizer_input)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize")]
+    fn pinyin_word_romanize_with_tokenizer_and_suffix(
+        tokenizer_input: AnyElement,
+        suffix: &str,
+    ) -> String {
+        pinyin_word_romanize_tokenizer_with_suffix_impl(tokenizer_input, suffix)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_name_romanize(origin: &str) -> String {
+        pinyin_name_romanize_impl(origin, None, &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_name_romanize")]
+    fn pinyin_name_romanize_with_suffix(origin: &str, suffix: &str) -> String {
+        pinyin_name_romanize_impl(origin, Some(suffix), &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_name_romanize")]
+    fn pinyin_name_romanize_with_options(
+        origin: &str,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> String {
+        let options = parse_romanize_options(&options);
+        pinyin_name_romanize_impl(origin, Some(suffix), &options)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_to_simplified(origin: &str) -> String {
+        variant_dictionary().to_simplified(origin)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_to_traditional(origin: &str) -> String {
+        variant_dictionary().to_traditional(origin)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_distance(a: &str, b: &str) -> f64 {
+        similarity::distance(&romanized_syllables(a), &romanized_syllables(b))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_similarity(a: &str, b: &str) -> f64 {
+        similarity::similarity(&romanized_syllables(a), &romanized_syllables(b))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_homophones(
+        origin: &str,
+    ) -> TableIterator<'static, (name!(term, String), name!(kind, String))> {
+        TableIterator::new(pinyin_homophones_impl(origin, None))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_homophones")]
+    fn pinyin_homophones_with_suffix(
+        origin: &str,
+        suffix: &str,
+    ) -> TableIterator<'static, (name!(term, String), name!(kind, String))> {
+        TableIterator::new(pinyin_homophones_impl(origin, Some(suffix)))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_to_hanzi(
+        origin: &str,
+        limit: i32,
+    ) -> TableIterator<'static, (name!(rank, i32), name!(candidate, String))> {
+        TableIterator::new(pinyin_to_hanzi_impl(origin, limit, None))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_to_hanzi")]
+    fn pinyin_to_hanzi_with_suffix(
+        origin: &str,
+        limit: i32,
+        suffix: &str,
+    ) -> TableIterator<'static, (name!(rank, i32), name!(candidate, String))> {
+        TableIterator::new(pinyin_to_hanzi_impl(origin, limit, Some(suffix)))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_sort_key(origin: &str) -> Vec<u8> {
+        pinyin_sort_key_impl(origin, None)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_sort_key")]
+    fn pinyin_sort_key_with_suffix(origin: &str, suffix: &str) -> Vec<u8> {
+        pinyin_sort_key_impl(origin, Some(suffix))
+    }
+
+    // Same letter as `pinyin_first_letter` with its default options, so the
+    // index bar and the first-letter grouping never disagree.
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_sort_bucket(origin: &str) -> String {
+        pinyin_first_letter_impl(origin, None, &FirstLetterOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_first_letter(origin: &str) -> String {
+        pinyin_first_letter_impl(origin, None, &FirstLetterOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_first_letter")]
+    fn pinyin_first_letter_with_suffix(origin: &str, suffix: &str) -> String {
+        pinyin_first_letter_impl(origin, Some(suffix), &FirstLetterOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_first_letter")]
+    fn pinyin_first_letter_with_options(
+        origin: &str,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> String {
+        let options = parse_first_letter_options(&options);
+        pinyin_first_letter_impl(origin, Some(suffix), &options)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_slugify(origin: &str) -> String {
+        pinyin_slugify_impl(origin, None, None)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_slugify")]
+    fn pinyin_slugify_with_max_len(origin: &str, max_len: i32) -> String {
+        pinyin_slugify_impl(origin, Some(max_len), None)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_slugify")]
+    fn pinyin_slugify_with_max_len_and_suffix(origin: &str, max_len: i32, suffix: &str) -> String {
+        pinyin_slugify_impl(origin, Some(max_len), Some(suffix))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_char_romanize_array(origin: &str) -> Vec<String> {
+        pinyin_char_romanize_array_impl(origin, None, &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_char_romanize_array")]
+    fn pinyin_char_romanize_array_with_suffix(origin: &str, suffix: &str) -> Vec<String> {
+        pinyin_char_romanize_array_impl(origin, Some(suffix), &RomanizeOptions::default())
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_word_romanize_array(origin: &str) -> Vec<String> {
+        pinyin_word_romanize_array_impl(origin, None)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_word_romanize_array")]
+    fn pinyin_word_romanize_array_with_suffix(origin: &str, suffix: &str) -> Vec<String> {
+        pinyin_word_romanize_array_impl(origin, Some(suffix))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_romanize_jsonb(origin: &str) -> pgrx::JsonB {
+        pinyin_romanize_jsonb_impl(origin, None)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_romanize_jsonb")]
+    fn pinyin_romanize_jsonb_with_suffix(origin: &str, suffix: &str) -> pgrx::JsonB {
+        pinyin_romanize_jsonb_impl(origin, Some(suffix))
+    }
+
+    #[allow(clippy::type_complexity)]
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_romanize_tokens(
+        origin: &str,
+    ) -> TableIterator<
+        'static,
+        (
+            name!(ord, i32),
+            name!(source, String),
+            name!(char_start, i32),
+            name!(char_end, i32),
+            name!(byte_start, i32),
+            name!(byte_end, i32),
+            name!(pinyin, String),
+            name!(kind, String),
+        ),
+    > {
+        TableIterator::new(romanized_token_rows(pinyin_romanize_tokens_impl(
+            origin,
+            None,
+            &RomanizeOptions::default(),
+        )))
+    }
+
+    #[allow(clippy::type_complexity)]
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_romanize_tokens")]
+    fn pinyin_romanize_tokens_with_suffix(
+        origin: &str,
+        suffix: &str,
+    ) -> TableIterator<
+        'static,
+        (
+            name!(ord, i32),
+            name!(source, String),
+            name!(char_start, i32),
+            name!(char_end, i32),
+            name!(byte_start, i32),
+            name!(byte_end, i32),
+            name!(pinyin, String),
+            name!(kind, String),
+        ),
+    > {
+        TableIterator::new(romanized_token_rows(pinyin_romanize_tokens_impl(
+            origin,
+            Some(suffix),
+            &RomanizeOptions::default(),
+        )))
+    }
+
+    #[allow(clippy::type_complexity)]
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_romanize_tokens")]
+    fn pinyin_romanize_tokens_with_options(
+        origin: &str,
+        suffix: &str,
+        options: pgrx::JsonB,
+    ) -> TableIterator<
+        'static,
+        (
+            name!(ord, i32),
+            name!(source, String),
+            name!(char_start, i32),
+            name!(char_end, i32),
+            name!(byte_start, i32),
+            name!(byte_end, i32),
+            name!(pinyin, String),
+            name!(kind, String),
+        ),
+    > {
+        TableIterator::new(romanized_token_rows(pinyin_romanize_tokens_impl(
+            origin,
+            Some(suffix),
+            &parse_romanize_options(&options),
+        )))
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe)]
+    fn pinyin_highlight(origin: &str, query: &str) -> String {
+        pinyin_highlight_impl(origin, query, "<b>", "</b>", None)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_highlight")]
+    fn pinyin_highlight_with_tags(
+        origin: &str,
+        query: &str,
+        start_tag: &str,
+        end_tag: &str,
+    ) -> String {
+        pinyin_highlight_impl(origin, query, start_tag, end_tag, None)
+    }
+
+    #[pg_extern(immutable, strict, parallel_safe, name = "pinyin_highlight")]
+    fn pinyin_highlight_with_tags_and_suffix(
+        origin: &str,
+        query: &str,
+        start_tag: &str,
+        end_tag: &str,
+        suffix: &str,
+    ) -> String {
+        pinyin_highlight_impl(origin, query, start_tag, end_tag, Some(suffix))
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_regex_phrase_patterns")]
+    fn pinyin_regex_phrase_patterns_default(value: &str) -> Option<Vec<String>> {
+        pinyin_regex_phrase_patterns_impl(value, false)
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_regex_phrase_patterns")]
+    fn pinyin_regex_phrase_patterns_with_generated(
+        value: &str,
+        generated_pinyin: bool,
+    ) -> Option<Vec<String>> {
+        pinyin_regex_phrase_patterns_impl(value, generated_pinyin)
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_regex_phrase_patterns")]
+    fn pinyin_regex_phrase_patterns_with_system(
+        value: &str,
+        generated_pinyin: bool,
+        system: &str,
+    ) -> Option<Vec<String>> {
+        pinyin_regex_phrase_patterns_with_systems_impl(value, generated_pinyin, system, "pinyin")
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_regex_phrase_patterns")]
+    fn pinyin_regex_phrase_patterns_with_systems(
+        value: &str,
+        generated_pinyin: bool,
+        query_system: &str,
+        column_system: &str,
+    ) -> Option<Vec<String>> {
+        pinyin_regex_phrase_patterns_with_systems_impl(
+            value,
+            generated_pinyin,
+            query_system,
+            column_system,
+        )
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_trgm_pattern")]
+    fn pinyin_trgm_pattern_default(value: &str) -> Option<String> {
+        pinyin_trgm_pattern_impl(value, false)
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_trgm_pattern")]
+    fn pinyin_trgm_pattern_with_initials(value: &str, initials: bool) -> Option<String> {
+        pinyin_trgm_pattern_impl(value, initials)
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_trgm_regex")]
+    fn pinyin_trgm_regex_default(value: &str) -> Option<String> {
+        pinyin_trgm_regex_impl(value, false)
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "pinyin_trgm_regex")]
+    fn pinyin_trgm_regex_with_initials(value: &str, initials: bool) -> Option<String> {
+        pinyin_trgm_regex_impl(value, initials)
     }
 
-    #[pg_extern(volatile, parallel_unsafe, name = "pinyin__seed_embedded_data")]
-    fn pinyin_seed_embedded_data_internal() -> bool {
-        seed_embedded_dictionary_data();
-        true
-    }
+    #[pg_extern(parallel_safe)]
+    fn pinyin_prs_start(input: Internal, len: i32) -> Internal {
+        let buffer = internal_ptr::<c_char>(input);
+        let text = unsafe { text_search_buffer_str(buffer, len.max(0) as usize) };
+        Internal::new(TextSearchParserState {
+            buffer,
+            tokens: split_text_search_tokens(text),
+            next: 0,
+        })
+    }
+
+    #[pg_extern(parallel_safe)]
+    fn pinyin_prs_gettoken(state: Internal, token: Internal, token_len: Internal) -> Internal {
+        let mut state = state;
+        let next = unsafe { state.get_mut::<TextSearchParserState>() }.and_then(|parser| {
+            let &(start, end, token_type) = parser.tokens.get(parser.next)?;
+            parser.next += 1;
+            unsafe {
+                *internal_ptr::<*mut c_char>(token) = parser.buffer.add(start);
+                *internal_ptr::<c_int>(token_len) = (end - start) as c_int;
+            }
+            Some(token_type)
+        });
+
+        // The parser API declares `internal` but callers read the token type as an int4.
+        Internal::from(Some(pg_sys::Datum::from(next.unwrap_or(0))))
+    }
+
+    #[pg_extern(parallel_safe)]
+    fn pinyin_prs_end(_state: Internal) {}
+
+    #[pg_extern(parallel_safe)]
+    fn pinyin_prs_lextype(_unused: Internal) -> Internal {
+        let lextypes = palloc_ts_lextypes(&[
+            (TS_TOKEN_ASCII_WORD, "asciiword", "Word, all ASCII"),
+            (TS_TOKEN_HAN, "han", "Han characters"),
+            (TS_TOKEN_BLANK, "blank", "Space symbols"),
+        ]);
+        Internal::from(Some(pg_sys::Datum::from(lextypes)))
+    }
+
+    #[pg_extern(parallel_safe)]
+    fn pinyin_dict_init(options: Internal) -> Internal {
+        let options = unsafe {
+            pgrx::PgList::<pg_sys::DefElem>::from_pg(internal_ptr::<pg_sys::List>(options))
+        };
+        let mut dictionary = TextSearchDictionary {
+            canonical_suffix: None,
+            initials: true,
+        };
+
+        for def in options.iter_ptr() {
+            let name = unsafe { CStr::from_ptr((*def).defname) }.to_string_lossy();
+            if name.eq_ignore_ascii_case("suffix") {
+                let value = unsafe { CStr::from_ptr(pg_sys::defGetString(def)) };
+                dictionary.canonical_suffix = canonicalize_table_suffix(&value.to_string_lossy());
+            } else if name.eq_ignore_ascii_case("initials") {
+                dictionary.initials = unsafe { pg_sys::defGetBoolean(def) };
+            } else {
+                error!("unrecognized pinyin dictionary parameter: \"{name}\"");
+            }
+        }
+
+        Internal::new(dictionary)
+    }
+
+    #[pg_extern(parallel_safe)]
+    fn pinyin_dict_lexize(
+        dictionary: Internal,
+        token: Internal,
+        token_len: Internal,
+        _state: Internal,
+    ) -> Internal {
+        let Some(dictionary) = (unsafe { dictionary.get::<TextSearchDictionary>() }) else {
+            error!("pinyin text search dictionary is not initialized");
+        };
+        let len = token_len.unwrap().map(|datum| datum.value()).unwrap_or(0);
+        let token = unsafe { text_search_buffer_str(internal_ptr::<c_char>(token), len) };
+        let lexemes = text_search_lexize_impl(token, dictionary);
+        Internal::from(Some(pg_sys::Datum::from(palloc_ts_lexemes(&lexemes))))
+    }
+
+    #[pg_extern(volatile, parallel_unsafe, name = "pinyin_clear_suffix_cache")]
+    fn pinyin_clear_suffix_cache_all() -> i64 {
+        clear_all_suffix_cache_impl()
+    }
+
+    #[pg_extern(volatile, strict, parallel_unsafe, name = "pinyin_clear_suffix_cache")]
+    fn pinyin_clear_suffix_cache_for_suffix(suffix: &str) -> bool {
+        clear_suffix_cache_impl(suffix)
+    }
+
+    #[pg_extern(volatile, parallel_safe, name = "pinyin__in_transaction_block")]
+    fn pinyin_in_transaction_block_internal() -> bool {
+        unsafe { pg_sys::IsTransactionBlock() }
+    }
+
+    #[pg_extern(volatile, parallel_unsafe, name = "pinyin__seed_embedded_data")]
+    fn pinyin_seed_embedded_data_internal() -> bool {
+        seed_embedded_dictionary_data();
+        true
+    }
+
+    extension_sql!(
+        r#"
+        CREATE SCHEMA IF NOT EXISTS pinyin;
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_mapping (
+          character text PRIMARY KEY,
+          pinyin text NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_token (
+          character text PRIMARY KEY,
+          category smallint NOT NULL CHECK (category IN (1, 2, 3))
+        );
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_words (
+          word text PRIMARY KEY,
+          pinyin text NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_surnames (
+          surname text PRIMARY KEY,
+          pinyin text NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_word_frequency (
+          word text PRIMARY KEY,
+          frequency bigint NOT NULL CHECK (frequency >= 0)
+        );
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_dictionary_meta (
+          singleton boolean PRIMARY KEY DEFAULT true CHECK (singleton),
+          version bigint NOT NULL DEFAULT 1
+        );
+
+        INSERT INTO pinyin.pinyin_dictionary_meta (singleton, version)
+        VALUES (true, 1)
+        ON CONFLICT (singleton) DO NOTHING;
+
+        CREATE OR REPLACE FUNCTION pinyin.pinyin_dictionary_bump_version()
+        RETURNS trigger
+        LANGUAGE plpgsql
+        AS $$
+        BEGIN
+          UPDATE pinyin.pinyin_dictionary_meta
+          SET version = version + 1
+          WHERE singleton;
+          RETURN NULL;
+        END;
+        $$;
+
+        DROP TRIGGER IF EXISTS pinyin_mapping_bump_version ON pinyin.pinyin_mapping;
+        CREATE TRIGGER pinyin_mapping_bump_version
+        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_mapping
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+
+        DROP TRIGGER IF EXISTS pinyin_words_bump_version ON pinyin.pinyin_words;
+        CREATE TRIGGER pinyin_words_bump_version
+        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_words
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+
+        DROP TRIGGER IF EXISTS pinyin_token_bump_version ON pinyin.pinyin_token;
+        CREATE TRIGGER pinyin_token_bump_version
+        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_token
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+
+        DROP TRIGGER IF EXISTS pinyin_surnames_bump_version ON pinyin.pinyin_surnames;
+        CREATE TRIGGER pinyin_surnames_bump_version
+        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_surnames
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+
+        DROP TRIGGER IF EXISTS pinyin_word_frequency_bump_version ON pinyin.pinyin_word_frequency;
+        CREATE TRIGGER pinyin_word_frequency_bump_version
+        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_word_frequency
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_dictionary_changes (
+          version bigint NOT NULL,
+          table_name text NOT NULL,
+          key text,
+          old_value text,
+          new_value text
+        );
+
+        CREATE INDEX IF NOT EXISTS pinyin_dictionary_changes_version_idx
+        ON pinyin.pinyin_dictionary_changes (version);
+
+        -- Records the rows touched by one statement under the version that the
+        -- statement's `*_bump_version` trigger just set; triggers of the same event
+        -- fire in name order, so `*_log_changes_*` always runs after the bump. A NULL
+        -- key stands for the whole table (TRUNCATE); a NULL old/new value means the
+        -- key did not exist before/after the statement.
+        CREATE OR REPLACE FUNCTION pinyin.pinyin_dictionary_log_changes()
+        RETURNS trigger
+        LANGUAGE plpgsql
+        AS $$
+        DECLARE
+          key_column text := TG_ARGV[0];
+          value_column text := TG_ARGV[1];
+          current_version bigint;
+        BEGIN
+          IF current_setting('pinyin.skip_change_log', true) = 'on' THEN
+            RETURN NULL;
+          END IF;
+
+          SELECT version INTO current_version
+          FROM pinyin.pinyin_dictionary_meta
+          WHERE singleton;
+
+          IF TG_OP = 'TRUNCATE' THEN
+            INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key)
+            VALUES (current_version, TG_TABLE_NAME, NULL);
+          ELSIF TG_OP = 'INSERT' THEN
+            INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key, new_value)
+            SELECT current_version, TG_TABLE_NAME, to_jsonb(r) ->> key_column, to_jsonb(r) ->> value_column
+            FROM new_rows AS r;
+          ELSIF TG_OP = 'UPDATE' THEN
+            INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key, old_value, new_value)
+            SELECT current_version, TG_TABLE_NAME, COALESCE(n.key, o.key), o.value, n.value
+            FROM (
+              SELECT to_jsonb(r) ->> key_column AS key, to_jsonb(r) ->> value_column AS value
+              FROM old_rows AS r
+            ) AS o
+            FULL JOIN (
+              SELECT to_jsonb(r) ->> key_column AS key, to_jsonb(r) ->> value_column AS value
+              FROM new_rows AS r
+            ) AS n ON n.key = o.key;
+          ELSE
+            INSERT INTO pinyin.pinyin_dictionary_changes (version, table_name, key, old_value)
+            SELECT current_version, TG_TABLE_NAME, to_jsonb(r) ->> key_column, to_jsonb(r) ->> value_column
+            FROM old_rows AS r;
+          END IF;
+          RETURN NULL;
+        END;
+        $$;
+
+        -- Attaches the `*_bump_version` trigger and the `*_log_changes_*` triggers to
+        -- a dictionary table, logging `key_column`/`value_column` of each changed row.
+        CREATE OR REPLACE FUNCTION pinyin.pinyin_dictionary_track_table(
+          dictionary_table regclass,
+          key_column text,
+          value_column text
+        )
+        RETURNS void
+        LANGUAGE plpgsql
+        SET client_min_messages = warning
+        AS $$
+        DECLARE
+          table_name text;
+          event text;
+        BEGIN
+          SELECT c.relname INTO table_name
+          FROM pg_class AS c
+          WHERE c.oid = dictionary_table;
+
+          EXECUTE format('DROP TRIGGER IF EXISTS %I ON %s', table_name || '_bump_version', dictionary_table);
+          EXECUTE format(
+            'CREATE TRIGGER %I
+             AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON %s
+             FOR EACH STATEMENT
+             EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version()',
+            table_name || '_bump_version',
+            dictionary_table
+          );
+
+          FOREACH event IN ARRAY ARRAY['insert', 'update', 'delete', 'truncate'] LOOP
+            EXECUTE format(
+              'DROP TRIGGER IF EXISTS %I ON %s',
+              table_name || '_log_changes_' || event,
+              dictionary_table
+            );
+            EXECUTE format(
+              'CREATE TRIGGER %I
+               AFTER %s ON %s
+               %s
+               FOR EACH STATEMENT
+               EXECUTE FUNCTION pinyin.pinyin_dictionary_log_changes(%L, %L)',
+              table_name || '_log_changes_' || event,
+              upper(event),
+              dictionary_table,
+              CASE event
+                WHEN 'insert' THEN 'REFERENCING NEW TABLE AS new_rows'
+                WHEN 'update' THEN 'REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows'
+                WHEN 'delete' THEN 'REFERENCING OLD TABLE AS old_rows'
+                ELSE ''
+              END,
+              key_column,
+              value_column
+            );
+          END LOOP;
+        END;
+        $$;
+
+        SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_mapping', 'character', 'pinyin');
+        SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_token', 'character', 'category');
+        SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_words', 'word', 'pinyin');
+        SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_surnames', 'surname', 'pinyin');
+        SELECT pinyin.pinyin_dictionary_track_table('pinyin.pinyin_word_frequency', 'word', 'frequency');
+
+        -- Canonical form of a user dictionary suffix, as used in overlay table names.
+        CREATE OR REPLACE FUNCTION pinyin.dictionary_table_suffix(suffix text)
+        RETURNS text
+        LANGUAGE sql
+        IMMUTABLE
+        AS $$
+          SELECT CASE
+            WHEN btrim(suffix) = '' THEN ''
+            ELSE '_' || lower(ltrim(btrim(suffix), '_'))
+          END;
+        $$;
+
+        CREATE OR REPLACE FUNCTION public.pinyin_track_suffix_tables(suffix text)
+        RETURNS integer
+        LANGUAGE plpgsql
+        AS $$
+        DECLARE
+          table_suffix text := pinyin.dictionary_table_suffix(suffix);
+          dictionary record;
+          tracked integer := 0;
+        BEGIN
+          IF table_suffix IN ('', '_') THEN
+            RAISE EXCEPTION 'dictionary table suffix cannot be empty';
+          END IF;
+          IF table_suffix !~ '^_[a-z0-9_]+$' THEN
+            RAISE EXCEPTION 'dictionary table suffix must contain only [A-Za-z0-9_]';
+          END IF;
+
+          FOR dictionary IN
+            SELECT to_regclass(format('pinyin.%I', t.base_name || table_suffix)) AS overlay, t.key_column, t.value_column
+            FROM (
+              VALUES
+                ('pinyin_mapping', 'character', 'pinyin'),
+                ('pinyin_token', 'character', 'category'),
+                ('pinyin_words', 'word', 'pinyin'),
+                ('pinyin_surnames', 'surname', 'pinyin'),
+                ('pinyin_word_frequency', 'word', 'frequency')
+            ) AS t(base_name, key_column, value_column)
+          LOOP
+            CONTINUE WHEN dictionary.overlay IS NULL;
+            PERFORM pinyin.pinyin_dictionary_track_table(
+              dictionary.overlay,
+              dictionary.key_column,
+              dictionary.value_column
+            );
+            tracked := tracked + 1;
+          END LOOP;
+          RETURN tracked;
+        END;
+        $$;
+
+        INSERT INTO pinyin.pinyin_mapping (character, pinyin)
+        VALUES (' ', ' ')
+        ON CONFLICT (character) DO NOTHING;
+        "#,
+        name = "pinyin_dictionary_tables",
+        requires = [pinyin_seed_embedded_data_internal],
+        bootstrap
+    );
+
+    extension_sql!(
+        r#"
+        CREATE OPERATOR public.<~> (
+          LEFTARG = text,
+          RIGHTARG = text,
+          FUNCTION = public.pinyin_distance,
+          COMMUTATOR = OPERATOR(public.<~>)
+        );
+        "#,
+        name = "pinyin_distance_operator",
+        requires = [pinyin_distance]
+    );
+
+    extension_sql!(
+        r#"
+        CREATE OR REPLACE FUNCTION pinyin.romanize_column_value(
+          value text,
+          mode text,
+          suffix text DEFAULT ''
+        )
+        RETURNS text
+        LANGUAGE plpgsql
+        IMMUTABLE
+        STRICT
+        PARALLEL SAFE
+        AS $$
+        BEGIN
+          CASE lower(mode)
+            WHEN 'char' THEN
+              RETURN public.pinyin_char_romanize(value, suffix);
+            WHEN 'word' THEN
+              RETURN public.pinyin_word_romanize(value, suffix);
+            WHEN 'initials' THEN
+              RETURN array_to_string(
+                ARRAY(
+                  SELECT left(syllable, 1)
+                  FROM regexp_split_to_table(public.pinyin_word_romanize(value, suffix), ' ') AS syllable
+                ),
+                ''
+              );
+            ELSE
+              RAISE EXCEPTION 'romanize mode must be one of char, word, initials (got "%")', mode;
+          END CASE;
+        END;
+        $$;
+
+        CREATE OR REPLACE FUNCTION pinyin.maintain_columns()
+        RETURNS trigger
+        LANGUAGE plpgsql
+        AS $$
+        DECLARE
+          source_column text := TG_ARGV[0];
+          target_column text := TG_ARGV[1];
+          row_value jsonb;
+        BEGIN
+          IF TG_WHEN <> 'BEFORE' OR TG_LEVEL <> 'ROW' OR TG_OP NOT IN ('INSERT', 'UPDATE') THEN
+            RAISE EXCEPTION 'pinyin.maintain_columns() must be a BEFORE INSERT OR UPDATE ... FOR EACH ROW trigger';
+          END IF;
+          IF TG_NARGS NOT BETWEEN 2 AND 4 THEN
+            RAISE EXCEPTION 'pinyin.maintain_columns() expects (source_column, target_column [, mode [, suffix]])';
+          END IF;
+
+          row_value := to_jsonb(NEW);
+          IF NOT row_value ? source_column THEN
+            RAISE EXCEPTION 'column "%" does not exist in %', source_column, TG_TABLE_NAME;
+          END IF;
+          IF NOT row_value ? target_column THEN
+            RAISE EXCEPTION 'column "%" does not exist in %', target_column, TG_TABLE_NAME;
+          END IF;
+
+          NEW := jsonb_populate_record(
+            NEW,
+            jsonb_build_object(
+              target_column,
+              pinyin.romanize_column_value(
+                row_value ->> source_column,
+                COALESCE(TG_ARGV[2], 'char'),
+                COALESCE(TG_ARGV[3], '')
+              )
+            )
+          );
+          RETURN NEW;
+        END;
+        $$;
+
+        CREATE OR REPLACE PROCEDURE public.pinyin_backfill(
+          target regclass,
+          source_column text,
+          target_column text,
+          mode text DEFAULT 'char',
+          suffix text DEFAULT '',
+          batch_size integer DEFAULT 10000,
+          INOUT updated bigint DEFAULT 0
+        )
+        LANGUAGE plpgsql
+        AS $$
+        DECLARE
+          partition regclass;
+          total_blocks bigint;
+          blocks_per_batch bigint;
+          first_block bigint;
+          batch_updated bigint;
+        BEGIN
+          IF batch_size IS NULL OR batch_size <= 0 THEN
+            RAISE EXCEPTION 'pinyin_backfill batch_size must be positive';
+          END IF;
+          IF (SELECT relkind FROM pg_class WHERE oid = target) NOT IN ('r', 'p') THEN
+            RAISE EXCEPTION 'pinyin_backfill target % must be a table or a partitioned table', target;
+          END IF;
+          -- Fail on a bad mode before touching any row.
+          PERFORM pinyin.romanize_column_value('', mode, suffix);
+          -- COMMIT after each batch would otherwise fail with "invalid transaction
+          -- termination" once the first batch is done.
+          IF public.pinyin__in_transaction_block() THEN
+            RAISE EXCEPTION 'pinyin_backfill cannot run inside a transaction block'
+              USING ERRCODE = 'active_sql_transaction',
+                    HINT = 'It commits after every batch; CALL it outside BEGIN ... COMMIT.';
+          END IF;
+
+          updated := 0;
+          -- A partitioned table has no storage of its own, so every leaf partition is
+          -- filled separately.
+          FOR partition IN
+            SELECT c.oid::regclass
+            FROM pg_class AS c
+            WHERE c.relkind = 'r'
+              AND (c.oid = target OR c.oid IN (SELECT relid FROM pg_partition_tree(target)))
+            ORDER BY c.oid::regclass::text
+          LOOP
+            -- Batches are block ranges sized from the planner statistics, so every
+            -- batch is a TID range scan; rows moved by earlier batches are skipped
+            -- because their value is already current.
+            total_blocks := pg_relation_size(partition) / current_setting('block_size')::bigint;
+            SELECT GREATEST(
+                     1,
+                     floor(
+                       batch_size / CASE
+                         WHEN relpages > 0 AND reltuples > 0 THEN reltuples / relpages
+                         ELSE 100
+                       END
+                     )
+                   )::bigint
+            INTO blocks_per_batch
+            FROM pg_class
+            WHERE oid = partition;
+
+            first_block := 0;
+            WHILE first_block < total_blocks LOOP
+              EXECUTE format(
+                'UPDATE %1$s AS t
+                 SET %3$I = v.romanized
+                 FROM (
+                   SELECT ctid AS row_ctid, pinyin.romanize_column_value(%2$I::text, $1, $2) AS romanized
+                   FROM %1$s
+                   WHERE ctid >= $3 AND ctid < $4
+                 ) AS v
+                 WHERE t.ctid = v.row_ctid
+                   AND t.%3$I IS DISTINCT FROM v.romanized',
+                partition,
+                source_column,
+                target_column
+              )
+              USING
+                mode,
+                suffix,
+                format('(%s,0)', first_block)::tid,
+                format('(%s,0)', first_block + blocks_per_batch)::tid;
+              GET DIAGNOSTICS batch_updated = ROW_COUNT;
+
+              updated := updated + batch_updated;
+              first_block := first_block + blocks_per_batch;
+              COMMIT;
+            END LOOP;
+          END LOOP;
+        END;
+        $$;
+
+        CREATE TABLE IF NOT EXISTS pinyin.pinyin_romanized_columns (
+          target regclass NOT NULL,
+          source_column text NOT NULL,
+          target_column text NOT NULL,
+          mode text NOT NULL DEFAULT 'char',
+          suffix text NOT NULL DEFAULT '',
+          refreshed_version bigint,
+          PRIMARY KEY (target, target_column)
+        );
+
+        -- Net changes between two dictionary versions: the first old and last new
+        -- value of every key whose value differs, and a NULL-key row per truncated
+        -- table.
+        CREATE OR REPLACE FUNCTION pinyin.dictionary_net_changes(old_version bigint, new_version bigint)
+        RETURNS TABLE (
+          table_name text,
+          key text,
+          old_value text,
+          new_value text
+        )
+        LANGUAGE sql
+        STABLE
+        AS $$
+          SELECT c.table_name, NULL::text, NULL::text, NULL::text
+          FROM pinyin.pinyin_dictionary_changes AS c
+          WHERE c.version > old_version
+            AND c.version <= new_version
+            AND c.key IS NULL
+          GROUP BY c.table_name
+
+          UNION ALL
+
+          SELECT n.table_name, n.key, n.old_value, n.new_value
+          FROM (
+            SELECT
+              c.table_name,
+              c.key,
+              (array_agg(c.old_value ORDER BY c.version))[1] AS old_value,
+              (array_agg(c.new_value ORDER BY c.version DESC))[1] AS new_value
+            FROM pinyin.pinyin_dictionary_changes AS c
+            WHERE c.version > old_version
+              AND c.version <= new_version
+              AND c.key IS NOT NULL
+            GROUP BY c.table_name, c.key
+          ) AS n
+          WHERE n.old_value IS DISTINCT FROM n.new_value;
+        $$;
+
+        -- Changed keys that can alter a column romanized in `mode` with `suffix`
+        -- between two versions; NULL when one of those tables was truncated. Char
+        -- mode ignores word changes.
+        CREATE OR REPLACE FUNCTION pinyin.dictionary_changed_keys(
+          old_version bigint,
+          new_version bigint,
+          mode text,
+          suffix text DEFAULT ''
+        )
+        RETURNS text[]
+        LANGUAGE sql
+        STABLE
+        AS $$
+          SELECT CASE
+            WHEN bool_or(n.key IS NULL) THEN NULL
+            ELSE COALESCE(array_agg(DISTINCT n.key), '{}')
+          END
+          FROM pinyin.dictionary_net_changes(old_version, new_version) AS n
+          CROSS JOIN LATERAL (SELECT pinyin.dictionary_table_suffix(suffix) AS table_suffix) AS s
+          WHERE n.table_name IN (
+              'pinyin_mapping',
+              'pinyin_token',
+              'pinyin_mapping' || s.table_suffix,
+              'pinyin_token' || s.table_suffix
+            )
+            OR (
+              lower(mode) <> 'char'
+              AND n.table_name IN ('pinyin_words', 'pinyin_words' || s.table_suffix)
+            );
+        $$;
+
+        CREATE OR REPLACE FUNCTION public.pinyin_dictionary_diff(old_version bigint, new_version bigint)
+        RETURNS TABLE (
+          table_name text,
+          key text,
+          old_value text,
+          new_value text,
+          change text
+        )
+        LANGUAGE plpgsql
+        STABLE
+        AS $$
+        #variable_conflict use_column
+        BEGIN
+          IF old_version > new_version THEN
+            RAISE EXCEPTION 'pinyin_dictionary_diff old_version must not be greater than new_version';
+          END IF;
+
+          RETURN QUERY
+          SELECT
+            n.table_name,
+            n.key,
+            n.old_value,
+            n.new_value,
+            CASE
+              WHEN n.key IS NULL THEN 'truncated'
+              WHEN n.old_value IS NULL THEN 'added'
+              WHEN n.new_value IS NULL THEN 'removed'
+              ELSE 'changed'
+            END
+          FROM pinyin.dictionary_net_changes(old_version, new_version) AS n
+          ORDER BY n.table_name, n.key NULLS FIRST;
+        END;
+        $$;
+
+        -- Rows whose source contains one of `keys` (every non-NULL source when `keys`
+        -- is NULL), with the romanization under the current dictionary.
+        CREATE OR REPLACE FUNCTION public.pinyin_affected_rows(
+          target regclass,
+          source_column text,
+          target_column text,
+          keys text[],
+          mode text DEFAULT 'char',
+          suffix text DEFAULT ''
+        )
+        RETURNS TABLE (
+          row_table regclass,
+          row_ctid tid,
+          source text,
+          stored text,
+          romanized text
+        )
+        LANGUAGE plpgsql
+        STABLE
+        AS $$
+        BEGIN
+          PERFORM pinyin.romanize_column_value('', mode, suffix);
+          -- Only rows containing a changed key can romanize differently, and of
+          -- those only rows whose stored value differs need an update.
+          RETURN QUERY EXECUTE format(
+            'SELECT a.row_table, a.row_ctid, a.source, a.stored, a.romanized
+             FROM (
+               SELECT
+                 tableoid::regclass AS row_table,
+                 ctid AS row_ctid,
+                 %2$I::text AS source,
+                 %3$I::text AS stored,
+                 pinyin.romanize_column_value(%2$I::text, $1, $2) AS romanized
+               FROM %1$s
+               WHERE %2$I IS NOT NULL
+                 AND ($3 IS NULL OR EXISTS (
+                   SELECT 1 FROM unnest($3) AS changed(key) WHERE strpos(%2$I::text, changed.key) > 0
+                 ))
+             ) AS a
+             WHERE a.stored IS DISTINCT FROM a.romanized',
+            target,
+            source_column,
+            target_column
+          )
+          USING mode, suffix, keys;
+        END;
+        $$;
+
+        CREATE OR REPLACE FUNCTION public.pinyin_affected_rows(
+          target regclass,
+          source_column text,
+          target_column text,
+          old_version bigint,
+          new_version bigint,
+          mode text DEFAULT 'char',
+          suffix text DEFAULT ''
+        )
+        RETURNS TABLE (
+          row_table regclass,
+          row_ctid tid,
+          source text,
+          stored text,
+          romanized text
+        )
+        LANGUAGE sql
+        STABLE
+        AS $$
+          SELECT a.row_table, a.row_ctid, a.source, a.stored, a.romanized
+          FROM public.pinyin_affected_rows(
+            target,
+            source_column,
+            target_column,
+            pinyin.dictionary_changed_keys(old_version, new_version, mode, suffix),
+            mode,
+            suffix
+          ) AS a;
+        $$;
+
+        CREATE OR REPLACE FUNCTION public.pinyin_register_column(
+          target regclass,
+          source_column text,
+          target_column text,
+          mode text DEFAULT 'char',
+          suffix text DEFAULT ''
+        )
+        RETURNS void
+        LANGUAGE plpgsql
+        AS $$
+        DECLARE
+          column_name text;
+        BEGIN
+          PERFORM pinyin.romanize_column_value('', mode, suffix);
+          FOREACH column_name IN ARRAY ARRAY[source_column, target_column] LOOP
+            IF NOT EXISTS (
+              SELECT 1
+              FROM pg_attribute
+              WHERE attrelid = target
+                AND attname = column_name
+                AND attnum > 0
+                AND NOT attisdropped
+            ) THEN
+              RAISE EXCEPTION 'column "%" does not exist in %', column_name, target;
+            END IF;
+          END LOOP;
+          -- Overlay changes must be logged for the refresh to see them.
+          IF pinyin.dictionary_table_suffix(suffix) <> '' THEN
+            PERFORM public.pinyin_track_suffix_tables(suffix);
+          END IF;
+
+          -- A new registration has never been refreshed, so its first refresh
+          -- rewrites the whole column.
+          INSERT INTO pinyin.pinyin_romanized_columns AS r
+            (target, source_column, target_column, mode, suffix, refreshed_version)
+          VALUES (target, source_column, target_column, lower(mode), suffix, NULL)
+          ON CONFLICT ON CONSTRAINT pinyin_romanized_columns_pkey DO UPDATE
+          SET source_column = EXCLUDED.source_column,
+              mode = EXCLUDED.mode,
+              suffix = EXCLUDED.suffix,
+              refreshed_version = NULL;
+        END;
+        $$;
+
+        CREATE OR REPLACE FUNCTION public.pinyin_unregister_column(target regclass, target_column text)
+        RETURNS boolean
+        LANGUAGE sql
+        AS $$
+          WITH removed AS (
+            DELETE FROM pinyin.pinyin_romanized_columns AS r
+            WHERE r.target = $1 AND r.target_column = $2
+            RETURNING 1
+          )
+          SELECT EXISTS (SELECT 1 FROM removed);
+        $$;
+
+        -- Registered columns behind the current dictionary version, with the number
+        -- of changed characters/words since their last refresh (NULL: full refresh).
+        CREATE OR REPLACE FUNCTION public.pinyin_stale_columns()
+        RETURNS TABLE (
+          target regclass,
+          source_column text,
+          target_column text,
+          refreshed_version bigint,
+          dictionary_version bigint,
+          changed_keys bigint
+        )
+        LANGUAGE sql
+        STABLE
+        AS $$
+          SELECT
+            r.target,
+            r.source_column,
+            r.target_column,
+            r.refreshed_version,
+            m.version,
+            CASE
+              WHEN r.refreshed_version IS NOT NULL THEN
+                cardinality(pinyin.dictionary_changed_keys(r.refreshed_version, m.version, r.mode, r.suffix))
+            END::bigint
+          FROM pinyin.pinyin_romanized_columns AS r
+          CROSS JOIN pinyin.pinyin_dictionary_meta AS m
+          WHERE m.singleton
+            AND (r.refreshed_version IS NULL OR r.refreshed_version < m.version)
+          ORDER BY r.target::text, r.target_column;
+        $$;
+
+        CREATE OR REPLACE PROCEDURE public.pinyin_refresh_columns(
+          batch_size integer DEFAULT 10000,
+          max_changed_keys integer DEFAULT 1000,
+          INOUT updated bigint DEFAULT 0
+        )
+        LANGUAGE plpgsql
+        AS $$
+        DECLARE
+          current_version bigint;
+          registrations pinyin.pinyin_romanized_columns[];
+          registration pinyin.pinyin_romanized_columns;
+          changed_keys text[];
+          column_updated bigint;
+        BEGIN
+          IF public.pinyin__in_transaction_block() THEN
+            RAISE EXCEPTION 'pinyin_refresh_columns cannot run inside a transaction block'
+              USING ERRCODE = 'active_sql_transaction',
+                    HINT = 'It commits after every column; CALL it outside BEGIN ... COMMIT.';
+          END IF;
+
+          SELECT version INTO current_version
+          FROM pinyin.pinyin_dictionary_meta
+          WHERE singleton;
+
+          -- Registrations of dropped tables are skipped.
+          registrations := ARRAY(
+            SELECT r
+            FROM pinyin.pinyin_romanized_columns AS r
+            JOIN pg_class AS c ON c.oid = r.target
+            WHERE r.refreshed_version IS NULL OR r.refreshed_version < current_version
+            ORDER BY r.target::text, r.target_column
+          );
+
+          updated := 0;
+          FOREACH registration IN ARRAY registrations LOOP
+            changed_keys := NULL;
+            IF registration.refreshed_version IS NOT NULL THEN
+              changed_keys := pinyin.dictionary_changed_keys(
+                registration.refreshed_version,
+                current_version,
+                registration.mode,
+                registration.suffix
+              );
+            END IF;
+
+            column_updated := 0;
+            IF changed_keys IS NULL OR cardinality(changed_keys) > max_changed_keys THEN
+              CALL public.pinyin_backfill(
+                registration.target,
+                registration.source_column,
+                registration.target_column,
+                registration.mode,
+                registration.suffix,
+                batch_size,
+                column_updated
+              );
+            ELSIF cardinality(changed_keys) > 0 THEN
+              -- tableoid keeps ctids of different partitions apart.
+              EXECUTE format(
+                'UPDATE %1$s AS t
+                 SET %2$I = a.romanized
+                 FROM public.pinyin_affected_rows($1, $2, $3, $4, $5, $6) AS a
+                 WHERE t.tableoid = a.row_table
+                   AND t.ctid = a.row_ctid',
+                registration.target,
+                registration.target_column
+              )
+              USING
+                registration.target,
+                registration.source_column,
+                registration.target_column,
+                changed_keys,
+                registration.mode,
+                registration.suffix;
+              GET DIAGNOSTICS column_updated = ROW_COUNT;
+            END IF;
+
+            UPDATE pinyin.pinyin_romanized_columns AS r
+            SET refreshed_version = current_version
+            WHERE r.target = registration.target
+              AND r.target_column = registration.target_column;
+            updated := updated + column_updated;
+            COMMIT;
+          END LOOP;
+        END;
+        $$;
+
+        -- Deletes logged changes up to `up_to_version`, by default everything every
+        -- registered column has already caught up to. Registrations behind the pruned
+        -- version are reset so their next refresh rewrites the whole column.
+        CREATE OR REPLACE FUNCTION public.pinyin_prune_dictionary_changes(up_to_version bigint DEFAULT NULL)
+        RETURNS bigint
+        LANGUAGE plpgsql
+        AS $$
+        DECLARE
+          prune_version bigint := up_to_version;
+          pruned bigint;
+        BEGIN
+          IF prune_version IS NULL THEN
+            SELECT COALESCE(
+                     (
+                       SELECT min(r.refreshed_version)
+                       FROM pinyin.pinyin_romanized_columns AS r
+                       JOIN pg_class AS c ON c.oid = r.target
+                     ),
+                     m.version
+                   )
+            INTO prune_version
+            FROM pinyin.pinyin_dictionary_meta AS m
+            WHERE m.singleton;
+          END IF;
+
+          DELETE FROM pinyin.pinyin_dictionary_changes AS c
+          WHERE c.version <= prune_version;
+          GET DIAGNOSTICS pruned = ROW_COUNT;
+
+          UPDATE pinyin.pinyin_romanized_columns AS r
+          SET refreshed_version = NULL
+          WHERE r.refreshed_version < prune_version;
+          RETURN pruned;
+        END;
+        $$;
+        "#,
+        name = "pinyin_maintain_columns",
+        requires = [
+            "pinyin_dictionary_tables",
+            pinyin_char_romanize_with_suffix,
+            pinyin_word_romanize_with_suffix
+        ]
+    );
+
+    extension_sql!(
+        r#"
+        SELECT public.pinyin__seed_embedded_data();
+        "#,
+        name = "pinyin_dictionary_seed",
+        requires = [pinyin_seed_embedded_data_internal]
+    );
+
+    extension_sql!(
+        r#"
+        CREATE TEXT SEARCH PARSER pinyin_parser (
+          START = pinyin_prs_start,
+          GETTOKEN = pinyin_prs_gettoken,
+          END = pinyin_prs_end,
+          LEXTYPES = pinyin_prs_lextype,
+          HEADLINE = pg_catalog.prsd_headline
+        );
+
+        CREATE TEXT SEARCH TEMPLATE pinyin_template (
+          INIT = pinyin_dict_init,
+          LEXIZE = pinyin_dict_lexize
+        );
+
+        CREATE TEXT SEARCH DICTIONARY pinyin_dict (
+          TEMPLATE = pinyin_template
+        );
+
+        CREATE TEXT SEARCH CONFIGURATION pinyin (
+          PARSER = pinyin_parser
+        );
+
+        ALTER TEXT SEARCH CONFIGURATION pinyin
+          ADD MAPPING FOR asciiword, han WITH pinyin_dict;
+        "#,
+        name = "pinyin_text_search",
+        requires = [
+            "pinyin_dictionary_tables",
+            pinyin_prs_start,
+            pinyin_prs_gettoken,
+            pinyin_prs_end,
+            pinyin_prs_lextype,
+            pinyin_dict_init,
+            pinyin_dict_lexize
+        ]
+    );
+
+    extension_sql!(
+        r#"
+        DO $pinyin_regex_phrase$
+        BEGIN
+          IF to_regtype('pdb.query') IS NOT NULL THEN
+            EXECUTE $create_function$
+              CREATE OR REPLACE FUNCTION public.pinyin_regex_phrase(
+                value text,
+                slope integer DEFAULT NULL,
+                max_expansions integer DEFAULT NULL,
+                generated_pinyin boolean DEFAULT false
+              )
+              RETURNS pdb.query
+              LANGUAGE plpgsql
+              STABLE
+              PARALLEL SAFE
+              AS $function$
+              DECLARE
+                patterns text[];
+              BEGIN
+                patterns := public.pinyin_regex_phrase_patterns(value, generated_pinyin);
+
+                IF patterns IS NULL THEN
+                  RETURN NULL;
+                END IF;
+
+                IF cardinality(patterns) = 0 THEN
+                  RETURN pdb.empty();
+                END IF;
+
+                IF cardinality(patterns) = 1 THEN
+                  RETURN pdb.regex(patterns[1]);
+                END IF;
+
+                IF max_expansions IS NOT NULL THEN
+                  RETURN pdb.regex_phrase(patterns, COALESCE(slope, 0), max_expansions);
+                END IF;
+
+                IF slope IS NOT NULL THEN
+                  RETURN pdb.regex_phrase(patterns, slope);
+                END IF;
+
+                RETURN pdb.regex_phrase(patterns);
+              END;
+              $function$;
+            $create_function$;
+          ELSE
+            EXECUTE $create_function$
+              CREATE OR REPLACE FUNCTION public.pinyin_regex_phrase(
+                value text,
+                slope integer DEFAULT NULL,
+                max_expansions integer DEFAULT NULL,
+                generated_pinyin boolean DEFAULT false
+              )
+              RETURNS text
+              LANGUAGE plpgsql
+              STABLE
+              PARALLEL SAFE
+              AS $function$
+              BEGIN
+                RAISE EXCEPTION
+                  'public.pinyin_regex_phrase requires CREATE EXTENSION pg_search before CREATE EXTENSION pg_pinyin';
+              END;
+              $function$;
+            $create_function$;
+          END IF;
+        END;
+        $pinyin_regex_phrase$;
+        "#,
+        name = "pinyin_regex_phrase_pg_search_helper",
+        requires = [pinyin_regex_phrase_patterns_with_generated]
+    );
+
+    #[cfg(any(test, feature = "pg_test"))]
+    #[pg_schema]
+    mod tests {
+        use pgrx::prelude::*;
+
+        fn seed_minimal_data() {
+            Spi::run(
+                "TRUNCATE TABLE pinyin.pinyin_mapping; \
+                 TRUNCATE TABLE pinyin.pinyin_token; \
+                 TRUNCATE TABLE pinyin.pinyin_words;",
+            )
+            .expect("failed to truncate dictionary tables");
+
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES
+                   (' ', ' '),
+                   ('我', '|wo|'),
+                   ('们', '|men|'),
+                   ('重', '|tong|zhong|chong|'),
+                   ('起', '|qi|'),
+                   ('郑', '|zheng|'),
+                   ('爽', '|shuang|');",
+            )
+            .expect("failed to seed pinyin_mapping");
+
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_words (word, pinyin) VALUES
+                   ('郑爽', '|zheng| |shuang|')
+                 ON CONFLICT (word) DO UPDATE SET pinyin = EXCLUDED.pinyin;",
+            )
+            .expect("failed to seed pinyin_words");
+        }
+
+        fn seed_suffix_tables(suffix: &str) {
+            let mapping_table = format!("pinyin.pinyin_mapping{}", suffix);
+            let words_table = format!("pinyin.pinyin_words{}", suffix);
+
+            Spi::run(&format!(
+                "CREATE TABLE IF NOT EXISTS {mapping_table} (
+                   character text PRIMARY KEY,
+                   pinyin text NOT NULL
+                 )"
+            ))
+            .expect("failed to create suffix mapping table");
+
+            Spi::run(&format!(
+                "CREATE TABLE IF NOT EXISTS {words_table} (
+                   word text PRIMARY KEY,
+                   pinyin text NOT NULL
+                 )"
+            ))
+            .expect("failed to create suffix words table");
+
+            Spi::run(&format!(
+                "TRUNCATE TABLE {mapping_table}; TRUNCATE TABLE {words_table};"
+            ))
+            .expect("failed to truncate suffix tables");
+
+            Spi::run(&format!(
+                "INSERT INTO {mapping_table} (character, pinyin) VALUES
+                   ('郑', '|zhengx|')
+                 ON CONFLICT (character) DO UPDATE SET pinyin = EXCLUDED.pinyin"
+            ))
+            .expect("failed to seed suffix mapping");
+
+            Spi::run(&format!(
+                "INSERT INTO {words_table} (word, pinyin) VALUES
+                   ('郑爽', '|zhengx| |shuangx|')
+                 ON CONFLICT (word) DO UPDATE SET pinyin = EXCLUDED.pinyin"
+            ))
+            .expect("failed to seed suffix words");
+        }
+
+        #[pg_test]
+        fn test_pinyin_char_romanize() {
+            seed_minimal_data();
+
+            let converted =
+                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('我ABC们123')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(converted, "wo abc men 123");
+        }
+
+        #[pg_test]
+        fn test_pinyin_word_romanize() {
+            seed_minimal_data();
+
+            let converted = Spi::get_one::<String>("SELECT public.pinyin_word_romanize('郑爽ABC')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(converted, "zheng shuang abc");
+        }
+
+        #[pg_test]
+        fn test_pinyin_word_romanize_with_tokenizer_passthrough() {
+            seed_minimal_data();
+
+            let converted = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize(ARRAY['郑爽', 'ABC']::text[])",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(converted, "zheng shuang abc");
+        }
+
+        #[pg_test]
+        fn test_dictionary_update_reflected() {
+            seed_minimal_data();
+
+            let before = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('我')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(before, "wo");
+
+            Spi::run("UPDATE pinyin.pinyin_mapping SET pinyin='|wo2|' WHERE character='我'")
+                .expect("failed to update mapping");
+
+            let after = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('我')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(after, "wo2");
+        }
+
+        #[pg_test]
+        fn test_polyphone_first_reading() {
+            seed_minimal_data();
+
+            let converted = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('重起')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(converted, "tong qi");
+        }
+
+        #[pg_test]
+        fn test_romanize_functions_are_immutable() {
+            let char_volatile = Spi::get_one::<String>(
+                "SELECT p.provolatile::text
+                 FROM pg_proc AS p
+                 WHERE p.oid = 'public.pinyin_char_romanize(text)'::regprocedure",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(char_volatile, "i");
+
+            let char_suffix_volatile = Spi::get_one::<String>(
+                "SELECT p.provolatile::text
+                 FROM pg_proc AS p
+                 WHERE p.oid = 'public.pinyin_char_romanize(text,text)'::regprocedure",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(char_suffix_volatile, "i");
+
+            let word_volatile = Spi::get_one::<String>(
+                "SELECT p.provolatile::text
+                 FROM pg_proc AS p
+                 WHERE p.oid = 'public.pinyin_word_romanize(text)'::regprocedure",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(word_volatile, "i");
+
+            let word_suffix_volatile = Spi::get_one::<String>(
+                "SELECT p.provolatile::text
+                 FROM pg_proc AS p
+                 WHERE p.oid = 'public.pinyin_word_romanize(text,text)'::regprocedure",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(word_suffix_volatile, "i");
+
+            let word_tokenizer_volatile = Spi::get_one::<String>(
+                "SELECT p.provolatile::text
+                 FROM pg_proc AS p
+                 WHERE p.oid = 'public.pinyin_word_romanize(anyelement)'::regprocedure",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(word_tokenizer_volatile, "i");
+
+            let word_tokenizer_suffix_volatile = Spi::get_one::<String>(
+                "SELECT p.provolatile::text
+                 FROM pg_proc AS p
+                 WHERE p.oid = 'public.pinyin_word_romanize(anyelement,text)'::regprocedure",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(word_tokenizer_suffix_volatile, "i");
+        }
+
+        #[pg_test]
+        fn test_generated_column_usage_raw_sql() {
+            seed_minimal_data();
+
+            Spi::run(
+                "CREATE TEMP TABLE pinyin_generated_demo (
+                   id bigserial PRIMARY KEY,
+                   description text NOT NULL,
+                   pinyin text GENERATED ALWAYS AS (public.pinyin_char_romanize(description)) STORED
+                 )",
+            )
+            .expect("failed to create generated column demo table");
+
+            Spi::run("INSERT INTO pinyin_generated_demo (description) VALUES ('郑爽ABC')")
+                .expect("failed to insert demo row");
+
+            let pinyin = Spi::get_one::<String>(
+                "SELECT pinyin FROM pinyin_generated_demo WHERE description = '郑爽ABC'",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(pinyin, "zheng shuang abc");
+        }
+
+        #[pg_test]
+        fn test_suffix_overlay_priority() {
+            seed_minimal_data();
+            seed_suffix_tables("_suffix1");
+
+            let base_char = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(base_char, "zheng shuang abc");
+
+            let suffix_char =
+                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(suffix_char, "zhengx shuang abc");
+
+            let suffix_word =
+                Spi::get_one::<String>("SELECT public.pinyin_word_romanize('郑爽ABC', '_suffix1')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(suffix_word, "zhengx shuangx abc");
+        }
+
+        #[pg_test]
+        fn test_suffix_overlay_update_reflected_after_cache_clear() {
+            seed_minimal_data();
+            seed_suffix_tables("_suffix1");
+
+            let before =
+                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(before, "zhengx shuang abc");
+
+            Spi::run(
+                "UPDATE pinyin.pinyin_mapping_suffix1
+                 SET pinyin = '|zhengy|'
+                 WHERE character = '郑'",
+            )
+            .expect("failed to update suffix mapping");
+
+            let still_cached =
+                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(still_cached, "zhengx shuang abc");
+
+            let cleared =
+                Spi::get_one::<bool>("SELECT public.pinyin_clear_suffix_cache('_suffix1')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert!(cleared);
+
+            let after =
+                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(after, "zhengy shuang abc");
+        }
+
+        #[pg_test]
+        fn test_pinyin_trgm_pattern() {
+            let pattern = Spi::get_one::<String>("SELECT public.pinyin_trgm_pattern('ZhengS')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(pattern, "%zheng% s%");
+
+            let initials = Spi::get_one::<String>("SELECT public.pinyin_trgm_pattern('zhs', true)")
+                .expect("SPI failed");
+            assert_eq!(initials, None);
+
+            let regex = Spi::get_one::<String>("SELECT public.pinyin_trgm_regex('zhengs')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(regex, "(^| )zheng[^ ]* s[^ ]*");
+
+            let invalid = Spi::get_one::<String>("SELECT public.pinyin_trgm_pattern('郑s')")
+                .expect("SPI failed");
+            assert_eq!(invalid, None);
+        }
+
+        #[pg_test]
+        fn test_pinyin_trgm_pattern_matches_char_romanize() {
+            seed_minimal_data();
+
+            let matched = Spi::get_one::<bool>(
+                "SELECT public.pinyin_char_romanize('郑爽ABC') LIKE public.pinyin_trgm_pattern('zhengs')
+                    AND public.pinyin_char_romanize('郑爽ABC') ~ public.pinyin_trgm_regex('zhengs')
+                    AND public.pinyin_char_romanize('郑爽ABC') ~ public.pinyin_trgm_regex('zs', true)
+                    AND NOT 'zheng wo shuang' ~ public.pinyin_trgm_regex('zhengs')
+                    AND 'zheng wo shuang' LIKE public.pinyin_trgm_pattern('zhengs')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert!(matched);
+        }
+
+        #[pg_test]
+        fn test_pinyin_romanize_tokens_offsets() {
+            seed_minimal_data();
+
+            let rows = Spi::get_one::<String>(
+                "SELECT string_agg(
+                   format('%s:%s-%s:%s-%s:%s:%s', source, char_start, char_end,
+                          byte_start, byte_end, pinyin, kind),
+                   '|' ORDER BY ord)
+                 FROM public.pinyin_romanize_tokens('我 郑爽ABC')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(
+                rows,
+                "我:0-1:0-3:wo:char|郑爽:2-4:4-10:zheng shuang:word|ABC:4-7:10-13:abc:passthrough"
+            );
+        }
+
+        #[pg_test]
+        fn test_pinyin_punctuation_policy() {
+            seed_minimal_data();
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_words (word, pinyin) VALUES ('重起', '|chong| |qi|')",
+            )
+            .expect("failed to seed word");
+
+            let dropped =
+                Spi::get_one::<String>("SELECT public.pinyin_word_romanize('重·起，ABC')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(dropped, "chong qi abc");
+
+            let separated = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('重·起，ABC', '', '{\"punctuation\": \"separator\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(separated, "tong qi abc");
+
+            let kept = Spi::get_one::<String>(
+                "SELECT public.pinyin_char_romanize('郑爽，ABC', '', '{\"punctuation\": \"keep\", \"normalize\": \"nfkc\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(kept, "zheng shuang , abc");
+
+            let tokenized = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize(ARRAY['郑爽', '，', 'ABC']::text[])",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(tokenized, "zheng shuang abc");
+        }
+
+        #[pg_test]
+        fn test_pinyin_fullwidth_normalization() {
+            seed_minimal_data();
+
+            let normalized = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('郑爽ＡＢＣ１２３', '', '{\"normalize\": \"nfkc\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(normalized, "zheng shuang abc123");
+
+            let raw = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ＡＢＣ')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(raw, "zheng shuang");
+
+            let offsets = Spi::get_one::<String>(
+                "SELECT format('%s:%s-%s:%s-%s', source, char_start, char_end, byte_start, byte_end)
+                 FROM public.pinyin_romanize_tokens('郑爽ＡＢＣ', '', '{\"normalize\": \"nfkc\"}')
+                 WHERE kind = 'passthrough'",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(offsets, "ＡＢＣ:2-5:6-15");
+        }
+
+        #[pg_test]
+        fn test_pinyin_latin_folding() {
+            seed_minimal_data();
+
+            let folded = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('Café 郑爽', '', '{\"fold_latin\": true}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(folded, "cafe zheng shuang");
+
+            let tones = Spi::get_one::<String>(
+                "SELECT public.pinyin_char_romanize(U&'zhèng Shua\\030Cng lǜ Straße', '', '{\"fold_latin\": true}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(tones, "zheng shuang lu strasse");
+
+            let unfolded = Spi::get_one::<String>("SELECT public.pinyin_word_romanize('Café')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(unfolded, "caf");
+        }
+
+        #[pg_test]
+        fn test_pinyin_variant_conversion() {
+            seed_minimal_data();
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES ('来', '|lai|'), ('來', '|lai|');
+                 INSERT INTO pinyin.pinyin_words (word, pinyin) VALUES ('重来', '|chong| |lai|');",
+            )
+            .expect("failed to seed variant words");
+
+            let simplified =
+                Spi::get_one::<String>("SELECT public.pinyin_to_simplified('阿爾法 頭髮 裏面')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(simplified, "阿尔法 头发 里面");
+
+            let traditional =
+                Spi::get_one::<String>("SELECT public.pinyin_to_traditional('头发 皇后 后来')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(traditional, "頭髮 皇后 後來");
+
+            let strict = Spi::get_one::<String>("SELECT public.pinyin_word_romanize('重來')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(strict, "tong lai");
+
+            let fallback = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('重來', '', '{\"variant_fallback\": true}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(fallback, "chong lai");
+        }
+
+        #[pg_test]
+        fn test_pinyin_zhuyin_style() {
+            seed_minimal_data();
+
+            let chars = Spi::get_one::<String>(
+                "SELECT public.pinyin_char_romanize('郑爽ABC', '', '{\"style\": \"zhuyin\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(chars, "ㄓㄥ ㄕㄨㄤ abc");
+
+            let words = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('我们郑爽', '', '{\"style\": \"zhuyin\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(words, "ㄨㄛ ㄇㄣ ㄓㄥ ㄕㄨㄤ");
+
+            let patterns = Spi::get_one::<Vec<String>>(
+                "SELECT public.pinyin_regex_phrase_patterns('ㄓㄥˋ ㄕㄨㄤˇ')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(patterns, vec!["zheng.*", "shuang.*"]);
+        }
+
+        #[pg_test]
+        fn test_pinyin_romanization_systems() {
+            seed_minimal_data();
+
+            let wade_giles = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('郑爽ABC', '', '{\"system\": \"wade_giles\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(wade_giles, "chêng shuang abc");
+
+            let yale = Spi::get_one::<String>(
+                "SELECT public.pinyin_char_romanize('郑爽', '', '{\"system\": \"yale\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(yale, "jeng shwang");
+
+            let from_wade_giles = Spi::get_one::<Vec<String>>(
+                "SELECT public.pinyin_regex_phrase_patterns('Ch''êng Shuang', false, 'wade_giles')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(from_wade_giles, vec!["cheng.*", "shuang.*"]);
 
-    extension_sql!(
-        r#"
-        CREATE SCHEMA IF NOT EXISTS pinyin;
+            let to_wade_giles = Spi::get_one::<Vec<String>>(
+                "SELECT public.pinyin_regex_phrase_patterns('zheng shuang', false, 'pinyin', 'wade_giles')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(to_wade_giles, vec!["chêng.*", "shuang.*"]);
+        }
 
-        CREATE TABLE IF NOT EXISTS pinyin.pinyin_mapping (
-          character text PRIMARY KEY,
-          pinyin text NOT NULL
-        );
+        #[pg_test]
+        fn test_pinyin_output_format() {
+            seed_minimal_data();
 
-        CREATE TABLE IF NOT EXISTS pinyin.pinyin_token (
-          character text PRIMARY KEY,
-          category smallint NOT NULL CHECK (category IN (1, 2, 3))
-        );
+            let slug = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('郑爽ABC', '', '{\"separator\": \"-\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(slug, "zheng-shuang-abc");
 
-        CREATE TABLE IF NOT EXISTS pinyin.pinyin_words (
-          word text PRIMARY KEY,
-          pinyin text NOT NULL
-        );
+            let title = Spi::get_one::<String>(
+                "SELECT public.pinyin_char_romanize('郑爽', '', '{\"separator\": \"\", \"case\": \"title\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(title, "ZhengShuang");
 
-        CREATE TABLE IF NOT EXISTS pinyin.pinyin_dictionary_meta (
-          singleton boolean PRIMARY KEY DEFAULT true CHECK (singleton),
-          version bigint NOT NULL DEFAULT 1
-        );
+            let words = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('郑爽ABC', '', '{\"join\": \"word\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(words, "zhengshuang abc");
 
-        INSERT INTO pinyin.pinyin_dictionary_meta (singleton, version)
-        VALUES (true, 1)
-        ON CONFLICT (singleton) DO NOTHING;
+            let upper_words = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('郑爽ABC', '', '{\"join\": \"word\", \"case\": \"title\", \"separator\": \"_\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(upper_words, "Zhengshuang_Abc");
+        }
 
-        CREATE OR REPLACE FUNCTION pinyin.pinyin_dictionary_bump_version()
-        RETURNS trigger
-        LANGUAGE plpgsql
-        AS $$
-        BEGIN
-          UPDATE pinyin.pinyin_dictionary_meta
-          SET version = version + 1
-          WHERE singleton;
-          RETURN NULL;
-        END;
-        $$;
+        #[pg_test]
+        fn test_pinyin_slugify() {
+            seed_minimal_data();
 
-        DROP TRIGGER IF EXISTS pinyin_mapping_bump_version ON pinyin.pinyin_mapping;
-        CREATE TRIGGER pinyin_mapping_bump_version
-        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_mapping
-        FOR EACH STATEMENT
-        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+            let slug =
+                Spi::get_one::<String>("SELECT public.pinyin_slugify('郑爽：ＡＢＣ 2026！Café')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(slug, "zheng-shuang-abc-2026-cafe");
 
-        DROP TRIGGER IF EXISTS pinyin_words_bump_version ON pinyin.pinyin_words;
-        CREATE TRIGGER pinyin_words_bump_version
-        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_words
-        FOR EACH STATEMENT
-        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+            let truncated = Spi::get_one::<String>("SELECT public.pinyin_slugify('郑爽ABC', 15)")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(truncated, "zheng-shuang");
+        }
 
-        DROP TRIGGER IF EXISTS pinyin_token_bump_version ON pinyin.pinyin_token;
-        CREATE TRIGGER pinyin_token_bump_version
-        AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON pinyin.pinyin_token
-        FOR EACH STATEMENT
-        EXECUTE FUNCTION pinyin.pinyin_dictionary_bump_version();
+        #[pg_test]
+        fn test_pinyin_numeral_readings() {
+            seed_minimal_data();
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES \
+                 ('零', '|ling|'), ('一', '|yi|'), ('二', '|er|'), ('三', '|san|'), \
+                 ('八', '|ba|'), ('十', '|shi|'), ('百', '|bai|'), ('〇', '|ling|'), ('六', '|liu|')",
+            )
+            .expect("failed to insert numeral mapping");
 
-        INSERT INTO pinyin.pinyin_mapping (character, pinyin)
-        VALUES (' ', ' ')
-        ON CONFLICT (character) DO NOTHING;
-        "#,
-        name = "pinyin_dictionary_tables",
-        requires = [pinyin_seed_embedded_data_internal],
-        bootstrap
-    );
+            let read = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('郑爽123', '', '{\"numerals\": \"read\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(read, "zheng shuang yi bai er shi san");
 
-    extension_sql!(
-        r#"
-        SELECT public.pinyin__seed_embedded_data();
-        "#,
-        name = "pinyin_dictionary_seed",
-        requires = [pinyin_seed_embedded_data_internal]
-    );
+            let digits = Spi::get_one::<String>(
+                "SELECT public.pinyin_char_romanize('138', '', '{\"numerals\": \"digits\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(digits, "yi san ba");
 
-    extension_sql!(
-        r#"
-        DO $pinyin_regex_phrase$
-        BEGIN
-          IF to_regtype('pdb.query') IS NOT NULL THEN
-            EXECUTE $create_function$
-              CREATE OR REPLACE FUNCTION public.pinyin_regex_phrase(
-                value text,
-                slope integer DEFAULT NULL,
-                max_expansions integer DEFAULT NULL,
-                generated_pinyin boolean DEFAULT false
-              )
-              RETURNS pdb.query
-              LANGUAGE plpgsql
-              STABLE
-              PARALLEL SAFE
-              AS $function$
-              DECLARE
-                patterns text[];
-              BEGIN
-                patterns := public.pinyin_regex_phrase_patterns(value, generated_pinyin);
+            let arabic = Spi::get_one::<String>(
+                "SELECT public.pinyin_word_romanize('二〇二六 一百二十三', '', '{\"numerals\": \"arabic\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(arabic, "2026 123");
+        }
 
-                IF patterns IS NULL THEN
-                  RETURN NULL;
-                END IF;
+        #[pg_test]
+        fn test_pinyin_sort_key() {
+            seed_minimal_data();
 
-                IF cardinality(patterns) = 0 THEN
-                  RETURN pdb.empty();
-                END IF;
+            let ordered = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(name ORDER BY public.pinyin_sort_key(name)) \
+                 FROM unnest(ARRAY['郑爽', 'zoe', '我们', 'Zoe', 'Abc', '123']) AS name",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(ordered, vec!["123", "Abc", "我们", "郑爽", "Zoe", "zoe"]);
 
-                IF cardinality(patterns) = 1 THEN
-                  RETURN pdb.regex(patterns[1]);
-                END IF;
+            let buckets = Spi::get_one::<Vec<String>>(
+                "SELECT ARRAY[public.pinyin_sort_bucket('郑爽'), public.pinyin_sort_bucket('abc'), \
+                 public.pinyin_sort_bucket('123')]",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(buckets, vec!["Z", "A", "#"]);
+        }
 
-                IF max_expansions IS NOT NULL THEN
-                  RETURN pdb.regex_phrase(patterns, COALESCE(slope, 0), max_expansions);
-                END IF;
+        #[pg_test]
+        fn test_pinyin_first_letter() {
+            seed_minimal_data();
+            seed_suffix_tables("_surname");
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES ('曾', '|ceng|zeng|'); \
+                 INSERT INTO pinyin.pinyin_words_surname (word, pinyin) VALUES ('曾', 'zeng')",
+            )
+            .expect("failed to insert surname readings");
+            Spi::run("SELECT public.pinyin_clear_suffix_cache('_surname')")
+                .expect("failed to clear suffix cache");
 
-                IF slope IS NOT NULL THEN
-                  RETURN pdb.regex_phrase(patterns, slope);
-                END IF;
+            let base = Spi::get_one::<String>("SELECT public.pinyin_first_letter('曾')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(base, "C");
 
-                RETURN pdb.regex_phrase(patterns);
-              END;
-              $function$;
-            $create_function$;
-          ELSE
-            EXECUTE $create_function$
-              CREATE OR REPLACE FUNCTION public.pinyin_regex_phrase(
-                value text,
-                slope integer DEFAULT NULL,
-                max_expansions integer DEFAULT NULL,
-                generated_pinyin boolean DEFAULT false
-              )
-              RETURNS text
-              LANGUAGE plpgsql
-              STABLE
-              PARALLEL SAFE
-              AS $function$
-              BEGIN
-                RAISE EXCEPTION
-                  'public.pinyin_regex_phrase requires CREATE EXTENSION pg_search before CREATE EXTENSION pg_pinyin';
-              END;
-              $function$;
-            $create_function$;
-          END IF;
-        END;
-        $pinyin_regex_phrase$;
-        "#,
-        name = "pinyin_regex_phrase_pg_search_helper",
-        requires = [pinyin_regex_phrase_patterns_with_generated]
-    );
+            let surname =
+                Spi::get_one::<String>("SELECT public.pinyin_first_letter('曾', '_surname')")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(surname, "Z");
 
-    #[cfg(any(test, feature = "pg_test"))]
-    #[pg_schema]
-    mod tests {
-        use pgrx::prelude::*;
+            let letters = Spi::get_one::<Vec<String>>(
+                "SELECT ARRAY[public.pinyin_first_letter('abc'), public.pinyin_first_letter('9号')]",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(letters, vec!["A", "#"]);
 
-        fn seed_minimal_data() {
+            let configured = Spi::get_one::<Vec<String>>(
+                "SELECT ARRAY[ \
+                   public.pinyin_first_letter('abc', '', '{\"ascii\": \"other\", \"other\": \"~\"}'), \
+                   public.pinyin_first_letter('9号', '', '{\"digits\": \"keep\"}')]",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(configured, vec!["~", "9"]);
+        }
+
+        #[pg_test]
+        fn test_pinyin_name_romanize() {
+            seed_minimal_data();
             Spi::run(
-                "TRUNCATE TABLE pinyin.pinyin_mapping; \
-                 TRUNCATE TABLE pinyin.pinyin_token; \
-                 TRUNCATE TABLE pinyin.pinyin_words;",
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES \
+                 ('单', '|dan|chan|shan|'), ('田', '|tian|'), ('芳', '|fang|'), ('仇', '|chou|qiu|')",
             )
-            .expect("failed to truncate dictionary tables");
+            .expect("failed to insert name mapping");
+
+            let name = Spi::get_one::<String>("SELECT public.pinyin_name_romanize('单田芳')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(name, "shan tian fang");
 
+            let plain = Spi::get_one::<String>("SELECT public.pinyin_word_romanize('单田芳')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(plain, "dan tian fang");
+
+            seed_suffix_tables("_names");
             Spi::run(
-                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES
-                   (' ', ' '),
-                   ('我', '|wo|'),
-                   ('们', '|men|'),
-                   ('重', '|tong|zhong|chong|'),
-                   ('起', '|qi|'),
-                   ('郑', '|zheng|'),
-                   ('爽', '|shuang|');",
+                "CREATE TABLE IF NOT EXISTS pinyin.pinyin_surnames_names ( \
+                   surname text PRIMARY KEY, pinyin text NOT NULL); \
+                 INSERT INTO pinyin.pinyin_surnames_names (surname, pinyin) VALUES ('仇', 'chou') \
+                 ON CONFLICT (surname) DO UPDATE SET pinyin = EXCLUDED.pinyin",
             )
-            .expect("failed to seed pinyin_mapping");
+            .expect("failed to seed surname overlay");
+            Spi::run("SELECT public.pinyin_clear_suffix_cache('_names')")
+                .expect("failed to clear suffix cache");
+
+            let overlay = Spi::get_one::<String>(
+                "SELECT public.pinyin_name_romanize('仇芳', '_names', '{\"case\": \"title\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(overlay, "Chou Fang");
 
             Spi::run(
-                "INSERT INTO pinyin.pinyin_words (word, pinyin) VALUES
-                   ('郑爽', '|zheng| |shuang|')
-                 ON CONFLICT (word) DO UPDATE SET pinyin = EXCLUDED.pinyin;",
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES ('單', '|dan|chan|shan|'); \
+                 DELETE FROM pinyin.pinyin_surnames WHERE surname = '單'",
             )
-            .expect("failed to seed pinyin_words");
+            .expect("failed to seed variant surname");
+
+            let folded = Spi::get_one::<String>(
+                "SELECT public.pinyin_name_romanize('單田芳', '', '{\"variant_fallback\": true}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(folded, "shan tian fang");
         }
 
-        fn seed_suffix_tables(suffix: &str) {
-            let mapping_table = format!("pinyin.pinyin_mapping{}", suffix);
-            let words_table = format!("pinyin.pinyin_words{}", suffix);
+        #[pg_test]
+        fn test_pinyin_homophones() {
+            seed_minimal_data();
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES \
+                 ('正', '|zheng|'), ('中', '|zhong|'), ('种', '|zhong|chong|'); \
+                 INSERT INTO pinyin.pinyin_words (word, pinyin) VALUES \
+                 ('正爽', 'zheng shuang'), ('中国', 'zhong guo')",
+            )
+            .expect("failed to insert homophone rows");
 
-            Spi::run(&format!(
-                "CREATE TABLE IF NOT EXISTS {mapping_table} (
-                   character text PRIMARY KEY,
-                   pinyin text NOT NULL
-                 )"
-            ))
-            .expect("failed to create suffix mapping table");
+            let zhong = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(term ORDER BY term) FROM public.pinyin_homophones('zhong')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(zhong, vec!["中", "种", "重"]);
 
-            Spi::run(&format!(
-                "CREATE TABLE IF NOT EXISTS {words_table} (
-                   word text PRIMARY KEY,
-                   pinyin text NOT NULL
-                 )"
-            ))
-            .expect("failed to create suffix words table");
+            let chong = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(term ORDER BY term) FROM public.pinyin_homophones('chóng')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(chong, vec!["种", "重"]);
 
-            Spi::run(&format!(
-                "TRUNCATE TABLE {mapping_table}; TRUNCATE TABLE {words_table};"
-            ))
-            .expect("failed to truncate suffix tables");
+            let word = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(term || ':' || kind) FROM public.pinyin_homophones('郑爽')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(word, vec!["正爽:word"]);
 
-            Spi::run(&format!(
-                "INSERT INTO {mapping_table} (character, pinyin) VALUES
-                   ('郑', '|zhengx|')
-                 ON CONFLICT (character) DO UPDATE SET pinyin = EXCLUDED.pinyin"
-            ))
-            .expect("failed to seed suffix mapping");
+            let joined = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(term ORDER BY term) FROM public.pinyin_homophones('zhengshuang')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(joined, vec!["正爽", "郑爽"]);
 
-            Spi::run(&format!(
-                "INSERT INTO {words_table} (word, pinyin) VALUES
-                   ('郑爽', '|zhengx| |shuangx|')
-                 ON CONFLICT (word) DO UPDATE SET pinyin = EXCLUDED.pinyin"
-            ))
-            .expect("failed to seed suffix words");
+            let char_homophones = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(term) FROM public.pinyin_homophones('郑')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(char_homophones, vec!["正"]);
         }
 
         #[pg_test]
-        fn test_pinyin_char_romanize() {
+        fn test_pinyin_to_hanzi() {
             seed_minimal_data();
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES ('正', '|zheng|'); \
+                 INSERT INTO pinyin.pinyin_words (word, pinyin) VALUES ('正爽', 'zheng shuang')",
+            )
+            .expect("failed to insert candidate rows");
 
-            let converted =
-                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('我ABC们123')")
+            let candidates = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(candidate ORDER BY rank) \
+                 FROM public.pinyin_to_hanzi('zhengshuang', 5)",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(candidates, vec!["正爽", "郑爽"]);
+
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_word_frequency (word, frequency) VALUES ('郑爽', 10)",
+            )
+            .expect("failed to insert word frequency");
+            let top = Spi::get_one::<String>(
+                "SELECT candidate FROM public.pinyin_to_hanzi('zheng shuang', 1)",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(top, "郑爽");
+
+            let mixed = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(candidate ORDER BY rank) \
+                 FROM public.pinyin_to_hanzi('wo zhong', 5)",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(mixed, vec!["我重"]);
+
+            let unknown =
+                Spi::get_one::<i64>("SELECT count(*) FROM public.pinyin_to_hanzi('xyz', 5)")
                     .expect("SPI failed")
                     .expect("no row returned");
-            assert_eq!(converted, "wo abc men 123");
+            assert_eq!(unknown, 0);
         }
 
         #[pg_test]
-        fn test_pinyin_word_romanize() {
+        fn test_pinyin_romanize_batch() {
             seed_minimal_data();
+            seed_suffix_tables("_batch");
 
-            let converted = Spi::get_one::<String>("SELECT public.pinyin_word_romanize('郑爽ABC')")
-                .expect("SPI failed")
-                .expect("no row returned");
-            assert_eq!(converted, "zheng shuang abc");
-        }
+            let words = Spi::get_one::<Vec<Option<String>>>(
+                "SELECT public.pinyin_word_romanize_batch(ARRAY['郑爽', NULL, '我们'])",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(
+                words,
+                vec![
+                    Some("zheng shuang".to_string()),
+                    None,
+                    Some("wo men".to_string())
+                ]
+            );
 
-        #[pg_test]
-        fn test_pinyin_word_romanize_with_tokenizer_passthrough() {
-            seed_minimal_data();
+            let chars = Spi::get_one::<Vec<Option<String>>>(
+                "SELECT public.pinyin_char_romanize(ARRAY['重起', '郑爽'])",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(
+                chars,
+                vec![
+                    Some("tong qi".to_string()),
+                    Some("zheng shuang".to_string())
+                ]
+            );
 
-            let converted = Spi::get_one::<String>(
-                "SELECT public.pinyin_word_romanize(ARRAY['郑爽', 'ABC']::text[])",
+            let matches_single = Spi::get_one::<bool>(
+                "SELECT public.pinyin_word_romanize_batch(ARRAY['郑爽我们'], '_batch') \
+                 = ARRAY[public.pinyin_word_romanize('郑爽我们', '_batch')]",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(converted, "zheng shuang abc");
+            assert!(matches_single);
+
+            let styled = Spi::get_one::<Vec<Option<String>>>(
+                "SELECT public.pinyin_word_romanize_batch(ARRAY['郑爽'], '', '{\"case\": \"title\"}')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(styled, vec![Some("Zheng Shuang".to_string())]);
+
+            let char_batch = Spi::get_one::<Vec<Option<String>>>(
+                "SELECT public.pinyin_char_romanize_batch(ARRAY['重起', NULL])",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(char_batch, vec![Some("tong qi".to_string()), None]);
         }
 
         #[pg_test]
-        fn test_dictionary_update_reflected() {
+        fn test_pinyin_maintain_columns() {
             seed_minimal_data();
+            seed_suffix_tables("_trigger");
+            Spi::run(
+                "CREATE TABLE pinyin_trigger_people ( \
+                   id integer PRIMARY KEY, \
+                   name text, \
+                   name_char text, \
+                   name_word text, \
+                   name_initials text \
+                 ); \
+                 CREATE TRIGGER people_char BEFORE INSERT OR UPDATE ON pinyin_trigger_people \
+                 FOR EACH ROW EXECUTE FUNCTION pinyin.maintain_columns('name', 'name_char'); \
+                 CREATE TRIGGER people_word BEFORE INSERT OR UPDATE ON pinyin_trigger_people \
+                 FOR EACH ROW EXECUTE FUNCTION pinyin.maintain_columns('name', 'name_word', 'word', '_trigger'); \
+                 CREATE TRIGGER people_initials BEFORE INSERT OR UPDATE ON pinyin_trigger_people \
+                 FOR EACH ROW EXECUTE FUNCTION pinyin.maintain_columns('name', 'name_initials', 'initials'); \
+                 INSERT INTO pinyin_trigger_people (id, name) VALUES (1, '郑爽'), (2, NULL)",
+            )
+            .expect("failed to create trigger table");
 
-            let before = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('我')")
-                .expect("SPI failed")
-                .expect("no row returned");
-            assert_eq!(before, "wo");
+            let inserted = Spi::get_one::<String>(
+                "SELECT concat_ws('|', name_char, name_word, name_initials) \
+                 FROM pinyin_trigger_people WHERE id = 1",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(inserted, "zheng shuang|zhengx shuangx|zs");
 
-            Spi::run("UPDATE pinyin.pinyin_mapping SET pinyin='|wo2|' WHERE character='我'")
-                .expect("failed to update mapping");
+            let null_source = Spi::get_one::<bool>(
+                "SELECT name_char IS NULL AND name_initials IS NULL \
+                 FROM pinyin_trigger_people WHERE id = 2",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert!(null_source);
 
-            let after = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('我')")
-                .expect("SPI failed")
-                .expect("no row returned");
-            assert_eq!(after, "wo2");
+            Spi::run("UPDATE pinyin_trigger_people SET name = '我们' WHERE id = 2")
+                .expect("failed to update trigger table");
+            let updated = Spi::get_one::<String>(
+                "SELECT concat_ws('|', name_char, name_word, name_initials) \
+                 FROM pinyin_trigger_people WHERE id = 2",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(updated, "wo men|wo men|wm");
         }
 
         #[pg_test]
-        fn test_polyphone_first_reading() {
+        fn test_pinyin_dictionary_change_tracking() {
             seed_minimal_data();
+            Spi::run(
+                "CREATE TABLE pinyin_tracked_people (id integer PRIMARY KEY, name text, name_py text); \
+                 SELECT public.pinyin_register_column('pinyin_tracked_people', 'name', 'name_py', 'word')",
+            )
+            .expect("failed to register column");
 
-            let converted = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('重起')")
-                .expect("SPI failed")
-                .expect("no row returned");
-            assert_eq!(converted, "tong qi");
-        }
-
-        #[pg_test]
-        fn test_romanize_functions_are_immutable() {
-            let char_volatile = Spi::get_one::<String>(
-                "SELECT p.provolatile::text
-                 FROM pg_proc AS p
-                 WHERE p.oid = 'public.pinyin_char_romanize(text)'::regprocedure",
+            let never_refreshed = Spi::get_one::<bool>(
+                "SELECT refreshed_version IS NULL AND changed_keys IS NULL \
+                 FROM public.pinyin_stale_columns() WHERE target = 'pinyin_tracked_people'::regclass",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(char_volatile, "i");
+            assert!(never_refreshed);
 
-            let char_suffix_volatile = Spi::get_one::<String>(
-                "SELECT p.provolatile::text
-                 FROM pg_proc AS p
-                 WHERE p.oid = 'public.pinyin_char_romanize(text,text)'::regprocedure",
+            // Pretend a refresh just ran, then change the dictionary.
+            Spi::run(
+                "UPDATE pinyin.pinyin_romanized_columns \
+                 SET refreshed_version = (SELECT version FROM pinyin.pinyin_dictionary_meta); \
+                 UPDATE pinyin.pinyin_words SET pinyin = '|zheng| |shuangx|' WHERE word = '郑爽'; \
+                 INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES ('田', '|tian|')",
+            )
+            .expect("failed to change dictionary");
+
+            let logged = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(c.table_name || ':' || c.key ORDER BY c.version) \
+                 FROM pinyin.pinyin_dictionary_changes AS c \
+                 JOIN pinyin.pinyin_romanized_columns AS r ON c.version > r.refreshed_version",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(char_suffix_volatile, "i");
+            assert_eq!(logged, vec!["pinyin_words:郑爽", "pinyin_mapping:田"]);
 
-            let word_volatile = Spi::get_one::<String>(
-                "SELECT p.provolatile::text
-                 FROM pg_proc AS p
-                 WHERE p.oid = 'public.pinyin_word_romanize(text)'::regprocedure",
+            let changed_keys = Spi::get_one::<i64>(
+                "SELECT changed_keys FROM public.pinyin_stale_columns() \
+                 WHERE target = 'pinyin_tracked_people'::regclass",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(word_volatile, "i");
+            assert_eq!(changed_keys, 2);
 
-            let word_suffix_volatile = Spi::get_one::<String>(
-                "SELECT p.provolatile::text
-                 FROM pg_proc AS p
-                 WHERE p.oid = 'public.pinyin_word_romanize(text,text)'::regprocedure",
+            // Frequencies are logged but cannot change a romanized column.
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_word_frequency (word, frequency) VALUES ('我们', 10)",
+            )
+            .expect("failed to change word frequencies");
+            let frequency_logged = Spi::get_one::<bool>(
+                "SELECT EXISTS ( \
+                   SELECT 1 FROM pinyin.pinyin_dictionary_changes \
+                   WHERE table_name = 'pinyin_word_frequency' AND key = '我们' AND new_value = '10')",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(word_suffix_volatile, "i");
-
-            let word_tokenizer_volatile = Spi::get_one::<String>(
-                "SELECT p.provolatile::text
-                 FROM pg_proc AS p
-                 WHERE p.oid = 'public.pinyin_word_romanize(anyelement)'::regprocedure",
+            assert!(frequency_logged);
+            let changed_keys = Spi::get_one::<i64>(
+                "SELECT changed_keys FROM public.pinyin_stale_columns() \
+                 WHERE target = 'pinyin_tracked_people'::regclass",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(word_tokenizer_volatile, "i");
+            assert_eq!(changed_keys, 2);
+
+            // Pruning keeps every change the registration has not caught up to.
+            Spi::run("SELECT public.pinyin_prune_dictionary_changes()")
+                .expect("failed to prune dictionary changes");
+            let (older, newer) = Spi::get_two::<i64, i64>(
+                "SELECT count(*) FILTER (WHERE c.version <= r.refreshed_version), \
+                        count(*) FILTER (WHERE c.version > r.refreshed_version) \
+                 FROM pinyin.pinyin_dictionary_changes AS c \
+                 CROSS JOIN pinyin.pinyin_romanized_columns AS r",
+            )
+            .expect("SPI failed");
+            assert_eq!((older, newer), (Some(0), Some(3)));
 
-            let word_tokenizer_suffix_volatile = Spi::get_one::<String>(
-                "SELECT p.provolatile::text
-                 FROM pg_proc AS p
-                 WHERE p.oid = 'public.pinyin_word_romanize(anyelement,text)'::regprocedure",
+            let unregistered = Spi::get_one::<bool>(
+                "SELECT public.pinyin_unregister_column('pinyin_tracked_people', 'name_py')",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(word_tokenizer_suffix_volatile, "i");
+            assert!(unregistered);
+            let remaining =
+                Spi::get_one::<i64>("SELECT count(*) FROM public.pinyin_stale_columns()")
+                    .expect("SPI failed")
+                    .expect("no row returned");
+            assert_eq!(remaining, 0);
         }
 
         #[pg_test]
-        fn test_generated_column_usage_raw_sql() {
+        fn test_pinyin_dictionary_diff() {
             seed_minimal_data();
-
             Spi::run(
-                "CREATE TEMP TABLE pinyin_generated_demo (
-                   id bigserial PRIMARY KEY,
-                   description text NOT NULL,
-                   pinyin text GENERATED ALWAYS AS (public.pinyin_char_romanize(description)) STORED
-                 )",
+                "CREATE TABLE pinyin_diff_people ( \
+                   id integer PRIMARY KEY, name text, name_char text, name_word text \
+                 ); \
+                 INSERT INTO pinyin_diff_people (id, name) VALUES (1, '郑爽'), (2, '我们'), (3, NULL); \
+                 UPDATE pinyin_diff_people \
+                 SET name_char = pinyin.romanize_column_value(name, 'char'), \
+                     name_word = pinyin.romanize_column_value(name, 'word'); \
+                 CREATE TEMP TABLE diff_start AS SELECT version FROM pinyin.pinyin_dictionary_meta; \
+                 UPDATE pinyin.pinyin_mapping SET pinyin = '|woo|' WHERE character = '我'; \
+                 UPDATE pinyin.pinyin_mapping SET pinyin = '|men|' WHERE character = '们'; \
+                 INSERT INTO pinyin.pinyin_words (word, pinyin) VALUES ('我们', '|wo| |men|'); \
+                 DELETE FROM pinyin.pinyin_words WHERE word = '郑爽'; \
+                 INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES ('田', '|tian|'); \
+                 DELETE FROM pinyin.pinyin_mapping WHERE character = '田'",
             )
-            .expect("failed to create generated column demo table");
+            .expect("failed to change dictionary");
+
+            // 们 was rewritten to the same reading and 田 added then removed,
+            // so neither shows up.
+            let diff = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(concat_ws(' ', d.table_name, d.key, d.old_value, d.new_value, d.change)) \
+                 FROM public.pinyin_dictionary_diff( \
+                   (SELECT version FROM diff_start), \
+                   (SELECT version FROM pinyin.pinyin_dictionary_meta) \
+                 ) AS d",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(
+                diff,
+                vec![
+                    "pinyin_mapping 我 |wo| |woo| changed",
+                    "pinyin_words 我们 |wo| |men| added",
+                    "pinyin_words 郑爽 |zheng| |shuang| removed",
+                ]
+            );
 
-            Spi::run("INSERT INTO pinyin_generated_demo (description) VALUES ('郑爽ABC')")
-                .expect("failed to insert demo row");
+            // Char mode only depends on pinyin_mapping, so only 我 matters.
+            let char_rows = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(concat_ws(':', a.row_table, a.source, a.stored, a.romanized)) \
+                 FROM public.pinyin_affected_rows( \
+                   'pinyin_diff_people', 'name', 'name_char', \
+                   (SELECT version FROM diff_start), \
+                   (SELECT version FROM pinyin.pinyin_dictionary_meta), \
+                   'char' \
+                 ) AS a",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(char_rows, vec!["pinyin_diff_people:我们:wo men:woo men"]);
+
+            // The new 我们 entry and the char fallback for 郑爽 give the
+            // stored readings back, so no word row has to change.
+            let word_rows = Spi::get_one::<i64>(
+                "SELECT count(*) \
+                 FROM public.pinyin_affected_rows( \
+                   'pinyin_diff_people', 'name', 'name_word', \
+                   (SELECT version FROM diff_start), \
+                   (SELECT version FROM pinyin.pinyin_dictionary_meta), \
+                   'word' \
+                 )",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(word_rows, 0);
 
-            let pinyin = Spi::get_one::<String>(
-                "SELECT pinyin FROM pinyin_generated_demo WHERE description = '郑爽ABC'",
+            let preview = Spi::get_one::<i64>(
+                "SELECT count(*) \
+                 FROM public.pinyin_affected_rows('pinyin_diff_people', 'name', 'name_char', ARRAY['我'])",
             )
             .expect("SPI failed")
             .expect("no row returned");
-            assert_eq!(pinyin, "zheng shuang abc");
+            assert_eq!(preview, 1);
         }
 
         #[pg_test]
-        fn test_suffix_overlay_priority() {
+        fn test_pinyin_similarity() {
             seed_minimal_data();
-            seed_suffix_tables("_suffix1");
+            Spi::run(
+                "INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES \
+                 ('曾', '|zeng|'), ('张', '|zhang|'), ('三', '|san|')",
+            )
+            .expect("failed to insert similarity mapping");
 
-            let base_char = Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC')")
+            let same = Spi::get_one::<f64>("SELECT public.pinyin_distance('郑爽', 'zheng shuang')")
                 .expect("SPI failed")
                 .expect("no row returned");
-            assert_eq!(base_char, "zheng shuang abc");
+            assert_eq!(same, 0.0);
 
-            let suffix_char =
-                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
-                    .expect("SPI failed")
-                    .expect("no row returned");
-            assert_eq!(suffix_char, "zhengx shuang abc");
+            let confusable = Spi::get_one::<f64>("SELECT public.pinyin_distance('郑爽', '曾爽')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(confusable, 0.25);
 
-            let suffix_word =
-                Spi::get_one::<String>("SELECT public.pinyin_word_romanize('郑爽ABC', '_suffix1')")
-                    .expect("SPI failed")
-                    .expect("no row returned");
-            assert_eq!(suffix_word, "zhengx shuangx abc");
+            let similarity = Spi::get_one::<f64>("SELECT public.pinyin_similarity('郑爽', '曾爽')")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(similarity, 0.875);
+
+            let ranked = Spi::get_one::<Vec<String>>(
+                "SELECT array_agg(name ORDER BY name <~> '郑爽') \
+                 FROM unnest(ARRAY['张三', '曾爽', '郑爽']) AS name",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(ranked, vec!["郑爽", "曾爽", "张三"]);
         }
 
         #[pg_test]
-        fn test_suffix_overlay_update_reflected_after_cache_clear() {
+        fn test_pinyin_romanize_array_variants() {
             seed_minimal_data();
-            seed_suffix_tables("_suffix1");
 
-            let before =
-                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
-                    .expect("SPI failed")
-                    .expect("no row returned");
-            assert_eq!(before, "zhengx shuang abc");
+            let chars = Spi::get_one::<Vec<String>>(
+                "SELECT public.pinyin_char_romanize_array('我 郑爽ABC')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(chars, vec!["wo", "zheng", "shuang", "abc"]);
 
-            Spi::run(
-                "UPDATE pinyin.pinyin_mapping_suffix1
-                 SET pinyin = '|zhengy|'
-                 WHERE character = '郑'",
+            let words = Spi::get_one::<Vec<String>>(
+                "SELECT public.pinyin_word_romanize_array('我 郑爽ABC')",
             )
-            .expect("failed to update suffix mapping");
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(words, vec!["wo", "zheng shuang", "abc"]);
 
-            let still_cached =
-                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
-                    .expect("SPI failed")
-                    .expect("no row returned");
-            assert_eq!(still_cached, "zhengx shuang abc");
+            let json = Spi::get_one::<String>(
+                "SELECT public.pinyin_romanize_jsonb('郑爽ABC')->0->>'kind'",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(json, "word");
+        }
 
-            let cleared =
-                Spi::get_one::<bool>("SELECT public.pinyin_clear_suffix_cache('_suffix1')")
-                    .expect("SPI failed")
-                    .expect("no row returned");
-            assert!(cleared);
+        #[pg_test]
+        fn test_pinyin_highlight() {
+            seed_minimal_data();
 
-            let after =
-                Spi::get_one::<String>("SELECT public.pinyin_char_romanize('郑爽ABC', '_suffix1')")
+            let highlighted =
+                Spi::get_one::<String>("SELECT public.pinyin_highlight('我们郑爽ABC', 'zhengs')")
                     .expect("SPI failed")
                     .expect("no row returned");
-            assert_eq!(after, "zhengy shuang abc");
+            assert_eq!(highlighted, "我们<b>郑爽</b>ABC");
+
+            let tagged = Spi::get_one::<String>(
+                "SELECT public.pinyin_highlight('我们郑爽ABC', 'men', '[', ']')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert_eq!(tagged, "我[们]郑爽ABC");
+        }
+
+        #[pg_test]
+        fn test_pinyin_text_search_configuration() {
+            seed_minimal_data();
+
+            let vector = Spi::get_one::<String>("SELECT to_tsvector('pinyin', '我郑爽ABC')::text")
+                .expect("SPI failed")
+                .expect("no row returned");
+            assert_eq!(
+                vector,
+                "'abc':3 'w':1 'wo':1 'zhengshuang':2 'zs':2 '我':1 '郑爽':2"
+            );
+
+            let matched = Spi::get_one::<bool>(
+                "SELECT to_tsvector('pinyin', '我郑爽ABC') @@ to_tsquery('pinyin', 'zs <-> abc')",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            assert!(matched);
         }
 
         #[pg_test]
@@ -0,0 +1,253 @@
+const DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+// Longest digit run read as one number (below 10^16, i.e. up to `千万亿`).
+const MAX_READ_DIGITS: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NumeralMode {
+    #[default]
+    Keep,
+    /// Arabic numbers are read as Mandarin numbers (`123` -> `一百二十三`).
+    Read,
+    /// Arabic numbers are read digit by digit (`138` -> `一三八`).
+    Digits,
+    /// Chinese numerals are written as Arabic digits (`二〇二六` -> `2026`).
+    Arabic,
+}
+
+impl NumeralMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "keep" => Some(Self::Keep),
+            "read" => Some(Self::Read),
+            "digits" => Some(Self::Digits),
+            "arabic" => Some(Self::Arabic),
+            _ => None,
+        }
+    }
+}
+
+fn digit_value(ch: char) -> Option<u64> {
+    match ch {
+        '〇' | '零' => Some(0),
+        '两' => Some(2),
+        _ => DIGITS
+            .iter()
+            .position(|digit| *digit == ch)
+            .map(|value| value as u64),
+    }
+}
+
+fn unit_value(ch: char) -> Option<u64> {
+    match ch {
+        '十' => Some(10),
+        '百' => Some(100),
+        '千' => Some(1_000),
+        '万' => Some(10_000),
+        '亿' => Some(100_000_000),
+        _ => None,
+    }
+}
+
+pub fn is_chinese_numeral(ch: char) -> bool {
+    digit_value(ch).is_some() || unit_value(ch).is_some()
+}
+
+/// Chinese digits of an ASCII digit run, one per digit (`0` is `零`).
+pub fn digits_to_chinese(digits: &str) -> String {
+    digits
+        .chars()
+        .filter_map(|ch| ch.to_digit(10))
+        .map(|digit| DIGITS[digit as usize])
+        .collect()
+}
+
+/// Chinese numeral of an ASCII digit run. Runs with a leading zero or more
+/// than 16 digits (phone numbers, codes) are read digit by digit instead.
+pub fn arabic_to_chinese(digits: &str) -> String {
+    let has_leading_zero = digits.len() > 1 && digits.starts_with('0');
+    if has_leading_zero || digits.len() > MAX_READ_DIGITS {
+        return digits_to_chinese(digits);
+    }
+    match digits.parse::<u64>() {
+        Ok(0) => DIGITS[0].to_string(),
+        Ok(value) => number_to_chinese(value, true),
+        Err(_) => digits_to_chinese(digits),
+    }
+}
+
+// `leading` drops the `一` of a leading `一十` (`十五`, but `一百一十五`).
+fn number_to_chinese(value: u64, leading: bool) -> String {
+    for (base, unit) in [(100_000_000, '亿'), (10_000, '万')] {
+        if value < base {
+            continue;
+        }
+        let mut out = number_to_chinese(value / base, leading);
+        out.push(unit);
+        let rest = value % base;
+        if rest > 0 {
+            if rest < base / 10 {
+                out.push(DIGITS[0]);
+            }
+            out.push_str(&number_to_chinese(rest, false));
+        }
+        return out;
+    }
+    section_to_chinese(value, leading)
+}
+
+fn section_to_chinese(value: u64, leading: bool) -> String {
+    let mut out = String::new();
+    let mut started = false;
+    let mut zero_pending = false;
+
+    for (place, unit) in [
+        (1_000, Some('千')),
+        (100, Some('百')),
+        (10, Some('十')),
+        (1, None),
+    ] {
+        let digit = (value / place % 10) as usize;
+        if digit == 0 {
+            zero_pending |= started;
+            continue;
+        }
+        if zero_pending {
+            out.push(DIGITS[0]);
+            zero_pending = false;
+        }
+        if !(digit == 1 && unit == Some('十') && leading && !started) {
+            out.push(DIGITS[digit]);
+        }
+        out.extend(unit);
+        started = true;
+    }
+    out
+}
+
+/// Arabic digits of a run of Chinese numerals: `二〇二六` -> `2026`,
+/// `一百二十三` -> `123`. Returns `None` for runs shorter than two characters
+/// and for runs that are not a well-formed number (`万一`).
+pub fn chinese_to_arabic(numeral: &str) -> Option<String> {
+    let chars: Vec<char> = numeral.chars().collect();
+    if chars.len() < 2 || !chars.iter().all(|ch| is_chinese_numeral(*ch)) {
+        return None;
+    }
+
+    // `两` only counts with a unit (`两百`); `两两` stays as it is.
+    if chars.iter().all(|ch| unit_value(*ch).is_none()) {
+        if chars.contains(&'两') {
+            return None;
+        }
+        return chars
+            .iter()
+            .map(|ch| digit_value(*ch).and_then(|digit| char::from_digit(digit as u32, 10)))
+            .collect();
+    }
+
+    let mut total = 0u64;
+    let mut wan_part = 0u64;
+    let mut seen_yi = false;
+    let mut section = Vec::new();
+    for &ch in &chars {
+        match ch {
+            '亿' => {
+                let value = wan_part + parse_section(&section)?;
+                if value == 0 || seen_yi {
+                    return None;
+                }
+                total = value * 100_000_000;
+                seen_yi = true;
+                wan_part = 0;
+                section.clear();
+            }
+            '万' => {
+                let value = parse_section(&section)?;
+                if value == 0 || wan_part > 0 {
+                    return None;
+                }
+                wan_part = value * 10_000;
+                section.clear();
+            }
+            _ => section.push(ch),
+        }
+    }
+    Some((total + wan_part + parse_section(&section)?).to_string())
+}
+
+// One group below 10000: digits followed by descending `千`, `百`, `十`, with
+// `零` as filler and an optional leading bare `十`.
+fn parse_section(chars: &[char]) -> Option<u64> {
+    let mut total = 0u64;
+    let mut digit: Option<u64> = None;
+    let mut last_unit = 10_000u64;
+
+    for (idx, &ch) in chars.iter().enumerate() {
+        if let Some(value) = digit_value(ch) {
+            if value == 0 {
+                if digit.is_some() {
+                    return None;
+                }
+                continue;
+            }
+            if digit.replace(value).is_some() {
+                return None;
+            }
+            continue;
+        }
+
+        let unit = unit_value(ch)?;
+        if unit >= last_unit {
+            return None;
+        }
+        let value = match digit.take() {
+            Some(value) => value,
+            None if idx == 0 && unit == 10 => 1,
+            None => return None,
+        };
+        total += value * unit;
+        last_unit = unit;
+    }
+    Some(total + digit.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arabic_numbers_are_read_with_units() {
+        assert_eq!(arabic_to_chinese("0"), "零");
+        assert_eq!(arabic_to_chinese("15"), "十五");
+        assert_eq!(arabic_to_chinese("115"), "一百一十五");
+        assert_eq!(arabic_to_chinese("1005"), "一千零五");
+        assert_eq!(arabic_to_chinese("100010"), "十万零一十");
+        assert_eq!(arabic_to_chinese("200000000"), "二亿");
+    }
+
+    #[test]
+    fn codes_are_read_digit_by_digit() {
+        assert_eq!(arabic_to_chinese("0138"), "零一三八");
+        assert_eq!(
+            arabic_to_chinese("12345678901234567"),
+            "一二三四五六七八九零一二三四五六七"
+        );
+    }
+
+    #[test]
+    fn chinese_numerals_become_arabic_digits() {
+        assert_eq!(chinese_to_arabic("二〇二六").as_deref(), Some("2026"));
+        assert_eq!(chinese_to_arabic("一百二十三").as_deref(), Some("123"));
+        assert_eq!(chinese_to_arabic("十五").as_deref(), Some("15"));
+        assert_eq!(chinese_to_arabic("两百").as_deref(), Some("200"));
+        assert_eq!(chinese_to_arabic("十万零一十").as_deref(), Some("100010"));
+    }
+
+    #[test]
+    fn malformed_numerals_are_rejected() {
+        assert_eq!(chinese_to_arabic("万一"), None);
+        assert_eq!(chinese_to_arabic("两两"), None);
+        assert_eq!(chinese_to_arabic("百千"), None);
+        assert_eq!(chinese_to_arabic("一"), None);
+    }
+}
@@ -0,0 +1,214 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use crate::zhuyin::split_tone;
+
+// (rare block, reading position, word count, character); smallest first.
+type CharRank = (bool, usize, Reverse<usize>, String);
+
+/// Reverse index of a dictionary: toneless reading to the characters and
+/// words that have it. Characters are listed under every reading they have,
+/// words under their dictionary reading.
+#[derive(Default)]
+pub struct ReadingIndex {
+    chars: HashMap<String, Vec<String>>,
+    words: HashMap<String, Vec<String>>,
+    max_word_syllables: usize,
+}
+
+impl ReadingIndex {
+    pub fn new(
+        char_map: &HashMap<String, String>,
+        word_map: &HashMap<String, String>,
+        frequencies: &HashMap<String, i64>,
+    ) -> Self {
+        // Without character frequencies, the number of dictionary words a
+        // character appears in stands in for how common it is.
+        let mut word_counts: HashMap<char, usize> = HashMap::new();
+        for word in word_map.keys() {
+            for ch in word.chars() {
+                *word_counts.entry(ch).or_default() += 1;
+            }
+        }
+
+        // Characters rank by block (CJK Unified Ideographs before the rarer
+        // extensions), then by how early the reading appears in their list,
+        // then by word count.
+        let mut chars: HashMap<String, Vec<CharRank>> = HashMap::new();
+        for (character, raw) in char_map {
+            let rare = !character.chars().all(is_common_ideograph);
+            let count = character
+                .chars()
+                .map(|ch| word_counts.get(&ch).copied().unwrap_or(0))
+                .sum();
+            let mut seen = Vec::new();
+            for key in raw.split('|').map(reading_key) {
+                if key.is_empty() || seen.contains(&key) {
+                    continue;
+                }
+                chars.entry(key.clone()).or_default().push((
+                    rare,
+                    seen.len(),
+                    Reverse(count),
+                    character.clone(),
+                ));
+                seen.push(key);
+            }
+        }
+
+        // Words rank by frequency, highest first.
+        let mut words: HashMap<String, Vec<(Reverse<i64>, String)>> = HashMap::new();
+        let mut max_word_syllables = 0;
+        for (word, raw) in word_map {
+            let key = reading_key(raw);
+            if key.is_empty() {
+                continue;
+            }
+            max_word_syllables = max_word_syllables.max(key.split(' ').count());
+            let frequency = frequencies.get(word).copied().unwrap_or(0);
+            words
+                .entry(key)
+                .or_default()
+                .push((Reverse(frequency), word.clone()));
+        }
+
+        let chars = chars
+            .into_iter()
+            .map(|(key, mut entries)| {
+                entries.sort_unstable();
+                (key, entries.into_iter().map(|(_, _, _, ch)| ch).collect())
+            })
+            .collect();
+        let words = words
+            .into_iter()
+            .map(|(key, mut entries)| {
+                entries.sort_unstable();
+                (key, entries.into_iter().map(|(_, word)| word).collect())
+            })
+            .collect();
+
+        Self {
+            chars,
+            words,
+            max_word_syllables,
+        }
+    }
+
+    /// Characters read as `reading` (a key from `reading_key`), best ranked
+    /// first.
+    pub fn chars(&self, reading: &str) -> &[String] {
+        self.chars.get(reading).map_or(&[], Vec::as_slice)
+    }
+
+    /// Words read as `reading` (a key from `reading_key`), most frequent
+    /// first.
+    pub fn words(&self, reading: &str) -> &[String] {
+        self.words.get(reading).map_or(&[], Vec::as_slice)
+    }
+
+    /// The `limit` best spellings of a syllable sequence, best first. Fewer
+    /// segments win, then lower ranks within each reading; a syllable
+    /// without any character makes the whole sequence unconvertible.
+    pub fn candidates(&self, syllables: &[String], limit: usize) -> Vec<String> {
+        if syllables.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        // best[end]: the `limit` best (segments, rank sum, text) paths
+        // covering syllables[..end]. Costs add up per segment, so keeping
+        // the best few per position is enough.
+        let mut best: Vec<Vec<(usize, usize, String)>> = vec![Vec::new(); syllables.len() + 1];
+        best[0].push((0, 0, String::new()));
+
+        for start in 0..syllables.len() {
+            let paths = keep_best(std::mem::take(&mut best[start]), limit);
+
+            let max_len = self.max_word_syllables.max(1);
+            for len in 1..=max_len.min(syllables.len() - start) {
+                let key = syllables[start..start + len].join(" ");
+                let chars = if len == 1 { self.chars(&key) } else { &[] };
+                let spellings = self
+                    .words(&key)
+                    .iter()
+                    .take(limit)
+                    .enumerate()
+                    .chain(chars.iter().take(limit).enumerate());
+                for (rank, spelling) in spellings {
+                    for (segments, ranks, text) in &paths {
+                        best[start + len].push((
+                            segments + 1,
+                            ranks + rank,
+                            format!("{text}{spelling}"),
+                        ));
+                    }
+                }
+            }
+        }
+
+        keep_best(std::mem::take(&mut best[syllables.len()]), limit)
+            .into_iter()
+            .map(|(_, _, text)| text)
+            .collect()
+    }
+}
+
+// The `limit` cheapest paths, keeping only the cheapest path per spelling.
+fn keep_best(mut paths: Vec<(usize, usize, String)>, limit: usize) -> Vec<(usize, usize, String)> {
+    paths.sort_unstable();
+    let mut seen = HashSet::new();
+    paths.retain(|(_, _, text)| seen.insert(text.clone()));
+    paths.truncate(limit);
+    paths
+}
+
+fn is_common_ideograph(ch: char) -> bool {
+    ('\u{4e00}'..='\u{9fff}').contains(&ch)
+}
+
+/// Lookup key of a romanized reading: lowercase syllables without tones,
+/// `ü`/`v` written as `u` like the bundled data, separated by single spaces.
+/// Dictionary-style `|a|b|` alternatives keep their first reading.
+pub fn reading_key(reading: &str) -> String {
+    reading
+        .split_whitespace()
+        .filter_map(|part| part.split('|').find(|alt| !alt.is_empty()))
+        .map(|syllable| match split_tone(syllable) {
+            Some((base, _)) => base.replace('v', "u"),
+            None => syllable.to_lowercase(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(rows: &[(&str, &str)]) -> HashMap<String, String> {
+        rows.iter()
+            .map(|&(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reading_keys_drop_tones_and_alternatives() {
+        assert_eq!(reading_key("|a|b|"), "a");
+        assert_eq!(reading_key("Zheng4  shuǎng"), "zheng shuang");
+        assert_eq!(reading_key("lv4 lü"), "lu lu");
+        assert_eq!(reading_key(""), "");
+    }
+
+    #[test]
+    fn candidates_prefer_whole_words() {
+        let chars = map(&[("郑", "|zheng|"), ("正", "|zheng|"), ("爽", "|shuang|")]);
+        let words = map(&[("郑爽", "zheng shuang"), ("正爽", "zheng shuang")]);
+        let frequencies = HashMap::from([("郑爽".to_string(), 10)]);
+        let index = ReadingIndex::new(&chars, &words, &frequencies);
+
+        let syllables = ["zheng", "shuang"].map(str::to_string);
+        assert_eq!(index.words("zheng shuang"), ["郑爽", "正爽"]);
+        assert_eq!(index.candidates(&syllables, 1), ["郑爽"]);
+        assert!(index.candidates(&["xyz".to_string()], 5).is_empty());
+        assert!(index.candidates(&syllables, 0).is_empty());
+    }
+}
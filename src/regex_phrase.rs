@@ -45,6 +45,11 @@ impl RegexTokenDictionary {
         self.token_count
     }
 
+    /// Longest-match segmentation of a query against the syllable tokens.
+    pub fn segment(&self, value: &str) -> Option<Vec<String>> {
+        segment_query(value, |lower, idx| self.match_token_len(lower, idx))
+    }
+
     fn match_token_len(&self, lower: &str, idx: usize) -> usize {
         let byte = lower.as_bytes()[idx];
         let bucket = &self.tokens_by_first[(byte - b'a') as usize];
@@ -73,78 +78,80 @@ pub fn tokens_from_pinyin_token_csv(csv_text: &str) -> Vec<String> {
     tokens
 }
 
-pub fn pinyin_regex_phrase_patterns(
-    value: &str,
-    generated_pinyin: bool,
-    dictionary: &RegexTokenDictionary,
-) -> Option<Vec<String>> {
+/// Splits an ASCII query into tokens: whitespace separates tokens, and
+/// `token_len(lower, idx)` gives the length of the token starting at byte `idx`
+/// of the lowercased query. `None` unless the query is non-empty ASCII letters
+/// and whitespace. Every query tokenizer goes through here so the regex and
+/// trigram paths split input the same way.
+pub fn segment_query(value: &str, token_len: impl Fn(&str, usize) -> usize) -> Option<Vec<String>> {
     if value.is_empty()
         || !value
             .bytes()
             .all(|byte| byte.is_ascii_alphabetic() || byte.is_ascii_whitespace())
     {
-        return Some(Vec::new());
+        return None;
     }
 
-    let token_count = pinyin_regex_phrase_token_count(value, dictionary).unwrap_or(0);
     let lower = value.to_ascii_lowercase();
-    let mut patterns = Vec::with_capacity(token_count);
+    let mut tokens = Vec::new();
     let mut idx = 0usize;
 
     while idx < lower.len() {
-        let byte = lower.as_bytes()[idx];
-        if byte.is_ascii_whitespace() {
+        if lower.as_bytes()[idx].is_ascii_whitespace() {
             idx += 1;
             continue;
         }
 
-        let token_len = dictionary.match_token_len(&lower, idx);
-        let token = &lower[idx..idx + token_len];
-        if generated_pinyin {
-            let mut pattern = String::with_capacity(token.len() + 5);
-            pattern.push_str(r".*\|");
-            pattern.push_str(token);
-            pattern.push_str(".*");
-            patterns.push(pattern);
-        } else {
-            let mut pattern = String::with_capacity(token.len() + 2);
-            pattern.push_str(token);
-            pattern.push_str(".*");
-            patterns.push(pattern);
-        }
-        idx += token_len;
+        let len = token_len(&lower, idx);
+        tokens.push(lower[idx..idx + len].to_string());
+        idx += len;
     }
 
-    Some(patterns)
+    Some(tokens)
 }
 
-pub fn pinyin_regex_phrase_token_count(
+pub fn pinyin_regex_phrase_patterns(
     value: &str,
+    generated_pinyin: bool,
     dictionary: &RegexTokenDictionary,
-) -> Option<usize> {
-    if value.is_empty()
-        || !value
-            .bytes()
-            .all(|byte| byte.is_ascii_alphabetic() || byte.is_ascii_whitespace())
-    {
-        return None;
-    }
-
-    let lower = value.to_ascii_lowercase();
-    let mut count = 0usize;
-    let mut idx = 0usize;
+) -> Option<Vec<String>> {
+    let Some(tokens) = dictionary.segment(value) else {
+        return Some(Vec::new());
+    };
+
+    let patterns = tokens
+        .iter()
+        .map(|token| {
+            if generated_pinyin {
+                let mut pattern = String::with_capacity(token.len() + 5);
+                pattern.push_str(r".*\|");
+                pattern.push_str(token);
+                pattern.push_str(".*");
+                pattern
+            } else {
+                let mut pattern = String::with_capacity(token.len() + 2);
+                pattern.push_str(token);
+                pattern.push_str(".*");
+                pattern
+            }
+        })
+        .collect();
 
-    while idx < lower.len() {
-        let byte = lower.as_bytes()[idx];
-        if byte.is_ascii_whitespace() {
-            idx += 1;
-            continue;
-        }
+    Some(patterns)
+}
 
-        let token_len = dictionary.match_token_len(&lower, idx);
-        count += 1;
-        idx += token_len;
-    }
+pub fn pinyin_query_tokens(value: &str, dictionary: &RegexTokenDictionary) -> Option<Vec<String>> {
+    dictionary
+        .segment(value)
+        .filter(|tokens| !tokens.is_empty())
+}
 
-    if count == 0 { None } else { Some(count) }
+pub fn pinyin_regex_phrase_token_count(
+    value: &str,
+    dictionary: &RegexTokenDictionary,
+) -> Option<usize> {
+    dictionary
+        .segment(value)
+        .map(|tokens| tokens.len())
+        .filter(|&count| count > 0)
 }
@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+
+use crate::zhuyin::{split_syllable, split_tone};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RomanizationSystem {
+    #[default]
+    Pinyin,
+    /// Wade-Giles with apostrophes and diacritics (`chêng`, `ch'ing`, `hsüeh`).
+    WadeGiles,
+    /// Yale romanization for Mandarin (`jeng`, `chyan`, `sywe`).
+    Yale,
+}
+
+impl RomanizationSystem {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pinyin" => Some(Self::Pinyin),
+            "wade_giles" | "wade-giles" | "wg" => Some(Self::WadeGiles),
+            "yale" => Some(Self::Yale),
+            _ => None,
+        }
+    }
+}
+
+/// Converts one pinyin syllable (toneless, tone-number or tone-mark spelling)
+/// to `system`. Tones 1-4 are kept as a trailing digit. Returns `None` for
+/// anything that is not a pinyin syllable.
+pub fn syllable_from_pinyin(syllable: &str, system: RomanizationSystem) -> Option<String> {
+    let (base, tone) = split_tone(syllable)?;
+    let (initial, final_) = split_syllable(&base)?;
+
+    let mut out = match system {
+        RomanizationSystem::Pinyin if is_final(initial.is_none(), &final_) => base,
+        RomanizationSystem::Pinyin => return None,
+        RomanizationSystem::WadeGiles => wade_giles(initial, &final_)?,
+        RomanizationSystem::Yale => yale(initial, &final_)?,
+    };
+    if (1..=4).contains(&tone) && system != RomanizationSystem::Pinyin {
+        out.push(char::from(b'0' + tone));
+    }
+    Some(out)
+}
+
+/// Converts every whitespace-separated syllable of a romanized phrase,
+/// keeping anything that is not a pinyin syllable unchanged.
+pub fn phrase_from_pinyin(phrase: &str, system: RomanizationSystem) -> String {
+    phrase
+        .split_whitespace()
+        .map(|syllable| {
+            syllable_from_pinyin(syllable, system).unwrap_or_else(|| syllable.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Syllable table of one romanization system, built from the pinyin syllable
+/// inventory (the `pinyin_token` list).
+pub struct SystemSyllables {
+    to_pinyin: HashMap<String, String>,
+    from_pinyin: Vec<(String, String)>,
+    max_len: usize,
+}
+
+impl SystemSyllables {
+    pub fn new(
+        system: RomanizationSystem,
+        pinyin_syllables: impl IntoIterator<Item = String>,
+    ) -> Self {
+        let mut from_pinyin = Vec::new();
+        for pinyin in pinyin_syllables {
+            if let Some(converted) = syllable_from_pinyin(&pinyin, system) {
+                from_pinyin.push((pinyin, converted));
+            }
+        }
+
+        // Exact spellings win. Spellings without diacritics (`ch'eng`) and
+        // then without apostrophes (`cheng`) only fill the remaining gaps, so
+        // an unmarked `tse` reads as the unaspirated `ze`, not `ce`.
+        let mut to_pinyin = HashMap::new();
+        for (pinyin, converted) in &from_pinyin {
+            to_pinyin
+                .entry(converted.clone())
+                .or_insert_with(|| pinyin.clone());
+        }
+        for (pinyin, converted) in &from_pinyin {
+            to_pinyin
+                .entry(fold_diacritics(converted))
+                .or_insert_with(|| pinyin.clone());
+        }
+        for aspirated in [false, true] {
+            for (pinyin, converted) in &from_pinyin {
+                if converted.contains('\'') == aspirated {
+                    to_pinyin
+                        .entry(fold_diacritics(converted).replace('\'', ""))
+                        .or_insert_with(|| pinyin.clone());
+                }
+            }
+        }
+        let max_len = to_pinyin
+            .keys()
+            .map(|key| key.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            to_pinyin,
+            from_pinyin,
+            max_len,
+        }
+    }
+
+    /// Rewrites a query written in this system as space-separated pinyin
+    /// syllables. Syllables may be separated by spaces or hyphens, or run
+    /// together; unknown text is kept as it is.
+    pub fn query_to_pinyin(&self, value: &str) -> String {
+        let lower: String = value
+            .chars()
+            .flat_map(char::to_lowercase)
+            .map(|ch| match ch {
+                '’' | '‘' | 'ʻ' | 'ʼ' | '`' => '\'',
+                _ => ch,
+            })
+            .collect();
+        let mut out = Vec::new();
+
+        for chunk in lower.split(|ch: char| ch.is_whitespace() || ch == '-' || ch.is_ascii_digit())
+        {
+            let chars: Vec<char> = chunk.chars().collect();
+            let mut idx = 0usize;
+            let mut unknown = String::new();
+
+            while idx < chars.len() {
+                let max_end = usize::min(chars.len(), idx + self.max_len);
+                let matched = (idx + 1..=max_end).rev().find_map(|end| {
+                    let candidate: String = chars[idx..end].iter().collect();
+                    self.to_pinyin.get(&candidate).map(|pinyin| (pinyin, end))
+                });
+
+                match matched {
+                    Some((pinyin, end)) => {
+                        if !unknown.is_empty() {
+                            out.push(std::mem::take(&mut unknown));
+                        }
+                        out.push(pinyin.clone());
+                        idx = end;
+                    }
+                    None => {
+                        unknown.push(chars[idx]);
+                        idx += 1;
+                    }
+                }
+            }
+            if !unknown.is_empty() {
+                out.push(unknown);
+            }
+        }
+
+        out.join(" ")
+    }
+
+    /// Spellings in this system of every pinyin syllable starting with
+    /// `prefix`, without spellings that extend another one in the list.
+    pub fn prefix_spellings(&self, prefix: &str) -> Vec<String> {
+        let mut spellings: Vec<&str> = self
+            .from_pinyin
+            .iter()
+            .filter(|(pinyin, _)| pinyin.starts_with(prefix))
+            .map(|(_, converted)| converted.as_str())
+            .collect();
+        spellings.sort_unstable();
+        spellings.dedup();
+
+        let mut out: Vec<String> = Vec::with_capacity(spellings.len());
+        for spelling in spellings {
+            if !out.iter().any(|kept| spelling.starts_with(kept.as_str())) {
+                out.push(spelling.to_string());
+            }
+        }
+        out
+    }
+}
+
+fn fold_diacritics(value: &str) -> String {
+    value
+        .chars()
+        .map(|ch| match ch {
+            'ê' => 'e',
+            'ü' => 'u',
+            _ => ch,
+        })
+        .collect()
+}
+
+// Finals as returned by `split_syllable`: `ong` only follows an initial,
+// `ueng` only stands alone (`weng`).
+fn is_final(standalone: bool, final_: &str) -> bool {
+    match final_ {
+        "a" | "o" | "e" | "ai" | "ei" | "ao" | "ou" | "an" | "en" | "ang" | "eng" | "er" | "i"
+        | "ia" | "io" | "ie" | "iao" | "iu" | "ian" | "in" | "iang" | "ing" | "iong" | "u"
+        | "ua" | "uo" | "uai" | "ui" | "uan" | "un" | "uang" | "v" | "ve" | "van" | "vn" => true,
+        "ong" => !standalone,
+        "ueng" => standalone,
+        _ => false,
+    }
+}
+
+fn wade_giles(initial: Option<&str>, final_: &str) -> Option<String> {
+    let Some(initial) = initial else {
+        if !is_final(true, final_) {
+            return None;
+        }
+        let standalone = match final_ {
+            "e" => "ê",
+            "en" => "ên",
+            "eng" => "êng",
+            "er" => "êrh",
+            "i" => "i",
+            "in" => "yin",
+            "ing" => "ying",
+            "ie" => "yeh",
+            "iu" => "yu",
+            "ian" => "yen",
+            "iong" => "yung",
+            "u" => "wu",
+            "ui" => "wei",
+            "un" => "wên",
+            "ueng" => "wêng",
+            "v" => "yü",
+            "ve" => "yüeh",
+            "van" => "yüan",
+            "vn" => "yün",
+            _ => {
+                if let Some(rest) = final_.strip_prefix('i') {
+                    return Some(format!("y{rest}"));
+                }
+                if let Some(rest) = final_.strip_prefix('u') {
+                    return Some(format!("w{rest}"));
+                }
+                final_
+            }
+        };
+        return Some(standalone.to_string());
+    };
+
+    if !final_.is_empty() && !is_final(false, final_) {
+        return None;
+    }
+    if final_.is_empty() {
+        let syllable = match initial {
+            "zh" => "chih",
+            "ch" => "ch'ih",
+            "sh" => "shih",
+            "r" => "jih",
+            "z" => "tzu",
+            "c" => "tz'u",
+            "s" => "ssu",
+            _ => return None,
+        };
+        return Some(syllable.to_string());
+    }
+
+    let initial_wg = match initial {
+        "b" => "p",
+        "p" => "p'",
+        "d" => "t",
+        "t" => "t'",
+        "g" => "k",
+        "k" => "k'",
+        "j" | "zh" => "ch",
+        "q" | "ch" => "ch'",
+        "x" => "hs",
+        "r" => "j",
+        "z" => "ts",
+        "c" => "ts'",
+        other => other,
+    };
+    let final_wg = match final_ {
+        "e" if matches!(initial, "g" | "k" | "h") => "o",
+        "e" => "ê",
+        "en" => "ên",
+        "eng" => "êng",
+        "er" => "êrh",
+        "ong" => "ung",
+        "ie" => "ieh",
+        "ian" => "ien",
+        "iong" => "iung",
+        "uo" if matches!(
+            initial,
+            "d" | "t" | "n" | "l" | "z" | "c" | "s" | "zh" | "ch" | "r"
+        ) =>
+        {
+            "o"
+        }
+        "ui" if matches!(initial, "g" | "k") => "uei",
+        "v" => "ü",
+        "ve" => "üeh",
+        "van" => "üan",
+        "vn" => "ün",
+        other => other,
+    };
+    Some(format!("{initial_wg}{final_wg}"))
+}
+
+fn yale(initial: Option<&str>, final_: &str) -> Option<String> {
+    let Some(initial) = initial else {
+        if !is_final(true, final_) {
+            return None;
+        }
+        let standalone = match final_ {
+            "i" => "yi".to_string(),
+            "in" => "yin".to_string(),
+            "ing" => "ying".to_string(),
+            "u" => "wu".to_string(),
+            "un" => "wen".to_string(),
+            _ => yale_final(final_).to_string(),
+        };
+        return Some(standalone);
+    };
+
+    if !final_.is_empty() && !is_final(false, final_) {
+        return None;
+    }
+    if final_.is_empty() {
+        let syllable = match initial {
+            "zh" => "jr",
+            "ch" => "chr",
+            "sh" => "shr",
+            "r" => "r",
+            "z" => "dz",
+            "c" => "tsz",
+            "s" => "sz",
+            _ => return None,
+        };
+        return Some(syllable.to_string());
+    }
+
+    let initial_yale = match initial {
+        "q" => "ch",
+        "x" => "sy",
+        "zh" => "j",
+        "z" => "dz",
+        "c" => "ts",
+        other => other,
+    };
+    let final_yale = match final_ {
+        "o" if matches!(initial, "b" | "p" | "m" | "f") => "wo",
+        other => yale_final(other),
+    };
+    // `sy` already carries the `y` glide (`xia` -> `sya`, `xue` -> `sywe`).
+    let final_yale = match final_yale.strip_prefix('y') {
+        Some(rest) if initial == "x" => rest,
+        _ => final_yale,
+    };
+    Some(format!("{initial_yale}{final_yale}"))
+}
+
+fn yale_final(final_: &str) -> &str {
+    match final_ {
+        "ao" => "au",
+        "ong" => "ung",
+        "ia" => "ya",
+        "io" => "yo",
+        "ie" => "ye",
+        "iao" => "yau",
+        "iu" => "you",
+        "ian" => "yan",
+        "iang" => "yang",
+        "iong" => "yung",
+        "ua" => "wa",
+        "uo" => "wo",
+        "uai" => "wai",
+        "ui" => "wei",
+        "uan" => "wan",
+        "un" => "wun",
+        "uang" => "wang",
+        "ueng" => "weng",
+        "v" => "yu",
+        "ve" => "ywe",
+        "van" => "ywan",
+        "vn" => "yun",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syllables_convert_to_other_systems() {
+        let wade_giles = |syllable| syllable_from_pinyin(syllable, RomanizationSystem::WadeGiles);
+        assert_eq!(wade_giles("zheng4").as_deref(), Some("chêng4"));
+        assert_eq!(wade_giles("qing").as_deref(), Some("ch'ing"));
+        assert_eq!(wade_giles("xue").as_deref(), Some("hsüeh"));
+        assert_eq!(
+            syllable_from_pinyin("zheng", RomanizationSystem::Yale).as_deref(),
+            Some("jeng")
+        );
+        assert_eq!(syllable_from_pinyin("abc", RomanizationSystem::Yale), None);
+    }
+
+    #[test]
+    fn phrases_keep_unknown_parts() {
+        assert_eq!(
+            phrase_from_pinyin("zheng shuang abc", RomanizationSystem::Yale),
+            "jeng shwang abc"
+        );
+    }
+
+    #[test]
+    fn queries_read_back_as_pinyin() {
+        let syllables = SystemSyllables::new(
+            RomanizationSystem::WadeGiles,
+            ["zheng", "ze", "ce", "qing"].map(str::to_string),
+        );
+        assert_eq!(syllables.query_to_pinyin("Ch'ing"), "qing");
+        assert_eq!(syllables.query_to_pinyin("chêng-tse"), "zheng ze");
+        assert_eq!(syllables.query_to_pinyin("ts'e xyz"), "ce xyz");
+    }
+}
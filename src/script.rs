@@ -0,0 +1,96 @@
+const HAN_RANGES: [(u32, u32); 15] = [
+    (0x2E80, 0x2EFF),   // CJK Radicals Supplement
+    (0x2F00, 0x2FDF),   // Kangxi Radicals
+    (0x3007, 0x3007),   // Ideographic number zero
+    (0x3400, 0x4DBF),   // Extension A
+    (0x4E00, 0x9FFF),   // CJK Unified Ideographs
+    (0xF900, 0xFAFF),   // Compatibility Ideographs
+    (0x20000, 0x2A6DF), // Extension B
+    (0x2A700, 0x2B73F), // Extension C
+    (0x2B740, 0x2B81F), // Extension D
+    (0x2B820, 0x2CEAF), // Extension E
+    (0x2CEB0, 0x2EBEF), // Extension F
+    (0x2EBF0, 0x2EE5F), // Extension I
+    (0x2F800, 0x2FA1F), // Compatibility Ideographs Supplement
+    (0x30000, 0x3134F), // Extension G
+    (0x31350, 0x323AF), // Extension H
+];
+
+const PUNCTUATION_RANGES: [(u32, u32); 21] = [
+    (0x00A1, 0x00A1),
+    (0x00A7, 0x00A7),
+    (0x00AB, 0x00AB),
+    (0x00B6, 0x00B7),
+    (0x00BB, 0x00BB),
+    (0x00BF, 0x00BF),
+    (0x2010, 0x2027), // General Punctuation (dashes, quotes, ellipsis)
+    (0x2030, 0x205E),
+    (0x2E00, 0x2E7F), // Supplemental Punctuation
+    (0x3001, 0x3003), // 、。〃
+    (0x3008, 0x3011), // 〈〉《》「」『』【】
+    (0x3014, 0x301F), // 〔〕〖〗〘〙〚〛〜〝〞〟
+    (0x3030, 0x3030),
+    (0x303D, 0x303D),
+    (0x30FB, 0x30FB), // Katakana middle dot
+    (0xFE10, 0xFE1F), // Vertical forms
+    (0xFE30, 0xFE6F), // CJK compatibility and small form variants
+    (0xFF01, 0xFF0F), // Full-width ASCII punctuation
+    (0xFF1A, 0xFF20),
+    (0xFF3B, 0xFF40),
+    (0xFF5B, 0xFF65),
+];
+
+fn in_ranges(ch: char, ranges: &[(u32, u32)]) -> bool {
+    let code = ch as u32;
+    ranges
+        .iter()
+        .any(|&(start, end)| start <= code && code <= end)
+}
+
+pub fn is_han_char(ch: char) -> bool {
+    in_ranges(ch, &HAN_RANGES)
+}
+
+pub fn is_han_text(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(is_han_char)
+}
+
+pub fn is_punctuation(ch: char) -> bool {
+    ch.is_ascii_punctuation() || in_ranges(ch, &PUNCTUATION_RANGES)
+}
+
+pub fn is_punctuation_text(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(is_punctuation)
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PunctuationPolicy {
+    /// Punctuation is removed before word matching, like whitespace (`郑·爽` matches `郑爽`).
+    #[default]
+    Drop,
+    /// Punctuation is kept as its own output token.
+    Keep,
+    /// Punctuation is removed from the output but ends the current word.
+    Separator,
+}
+
+impl PunctuationPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "drop" => Some(Self::Drop),
+            "keep" => Some(Self::Keep),
+            "separator" => Some(Self::Separator),
+            _ => None,
+        }
+    }
+
+    /// Whether punctuation survives tokenization, as a word boundary or an output token.
+    pub fn keeps_token(self) -> bool {
+        self != Self::Drop
+    }
+
+    /// Whether punctuation appears in the romanized output.
+    pub fn emits_token(self) -> bool {
+        self == Self::Keep
+    }
+}
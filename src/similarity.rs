@@ -0,0 +1,121 @@
+use crate::zhuyin::{is_final, split_syllable, split_tone};
+
+const TONE_COST: f64 = 0.1;
+const CONFUSABLE_COST: f64 = 0.25;
+const PART_COST: f64 = 0.5;
+
+// Pairs commonly merged in regional accents and pinyin input typos.
+const CONFUSABLE_INITIALS: [(&str, &str); 6] = [
+    ("zh", "z"),
+    ("ch", "c"),
+    ("sh", "s"),
+    ("n", "l"),
+    ("f", "h"),
+    ("r", "l"),
+];
+
+const CONFUSABLE_FINALS: [(&str, &str); 5] = [
+    ("an", "ang"),
+    ("en", "eng"),
+    ("in", "ing"),
+    ("ian", "iang"),
+    ("uan", "uang"),
+];
+
+fn is_confusable(pairs: &[(&str, &str)], left: &str, right: &str) -> bool {
+    pairs
+        .iter()
+        .any(|&(a, b)| (left, right) == (a, b) || (left, right) == (b, a))
+}
+
+fn part_cost(pairs: &[(&str, &str)], left: &str, right: &str) -> f64 {
+    if left == right {
+        0.0
+    } else if is_confusable(pairs, left, right) {
+        CONFUSABLE_COST
+    } else {
+        PART_COST
+    }
+}
+
+/// Substitution cost of two romanized syllables, between 0 and 1. Pinyin
+/// syllables are compared by initial, final and tone; anything that does not
+/// parse to a known final (ASCII runs such as `hello`) only matches itself.
+pub fn syllable_distance(left: &str, right: &str) -> f64 {
+    if left == right {
+        return 0.0;
+    }
+    let parse = |syllable: &str| {
+        let (base, tone) = split_tone(syllable)?;
+        let (initial, final_) = split_syllable(&base)?;
+        is_final(&final_).then(|| (initial.unwrap_or_default(), final_, tone))
+    };
+    let (Some(left), Some(right)) = (parse(left), parse(right)) else {
+        return 1.0;
+    };
+
+    let mut cost = part_cost(&CONFUSABLE_INITIALS, left.0, right.0)
+        + part_cost(&CONFUSABLE_FINALS, &left.1, &right.1);
+    // Toneless readings match every tone.
+    if left.2 != 0 && right.2 != 0 && left.2 != right.2 {
+        cost += TONE_COST;
+    }
+    cost.min(1.0)
+}
+
+/// Weighted edit distance over syllables: insertions and deletions cost 1,
+/// substitutions cost `syllable_distance`.
+pub fn distance<S: AsRef<str>>(left: &[S], right: &[S]) -> f64 {
+    let mut previous: Vec<f64> = (0..=right.len()).map(|idx| idx as f64).collect();
+    let mut current = vec![0.0; right.len() + 1];
+
+    for (i, left_syllable) in left.iter().enumerate() {
+        current[0] = (i + 1) as f64;
+        for (j, right_syllable) in right.iter().enumerate() {
+            let substitution =
+                previous[j] + syllable_distance(left_syllable.as_ref(), right_syllable.as_ref());
+            current[j + 1] = substitution
+                .min(previous[j + 1] + 1.0)
+                .min(current[j] + 1.0);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[right.len()]
+}
+
+/// `1 - distance / longer length`, so 1 means the same reading and 0 means
+/// nothing in common. Two empty inputs are identical.
+pub fn similarity<S: AsRef<str>>(left: &[S], right: &[S]) -> f64 {
+    let longer = left.len().max(right.len());
+    if longer == 0 {
+        return 1.0;
+    }
+    1.0 - distance(left, right) / longer as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_pinyin_words_only_match_themselves() {
+        assert_eq!(syllable_distance("hello", "world"), 1.0);
+        assert_eq!(syllable_distance("abc", "abd"), 1.0);
+        assert_eq!(syllable_distance("abc", "abc"), 0.0);
+    }
+
+    #[test]
+    fn pinyin_syllables_are_costed_by_part() {
+        assert_eq!(syllable_distance("zheng", "zeng"), 0.25);
+        assert_eq!(syllable_distance("shi", "si"), 0.25);
+        assert_eq!(syllable_distance("ma1", "ma3"), 0.1);
+    }
+
+    #[test]
+    fn phrases_are_compared_by_edit_distance() {
+        assert_eq!(distance(&["zheng", "shuang"], &["zeng", "shuang"]), 0.25);
+        assert_eq!(distance(&["zheng"], &["zheng", "shuang"]), 1.0);
+        assert_eq!(similarity(&["zheng", "shuang"], &["zeng", "shuang"]), 0.875);
+        assert_eq!(similarity::<&str>(&[], &[]), 1.0);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::zhuyin::split_tone;
+
+const LEVEL_SEPARATOR: u8 = 0x00;
+const SYLLABLE_SEPARATOR: u8 = 0x01;
+
+/// Builds a three-level binary key from the romanized tokens of `original`
+/// (`(romanized, from_dictionary)` pairs): syllable spellings first, then
+/// tones, then the original text, so texts with the same reading still sort
+/// in a fixed order. Keys compare bytewise.
+pub fn sort_key<'a>(tokens: impl IntoIterator<Item = (&'a str, bool)>, original: &str) -> Vec<u8> {
+    let mut spellings = Vec::new();
+    let mut tones = Vec::new();
+
+    for (romanized, from_dictionary) in tokens {
+        for syllable in romanized.split_whitespace() {
+            let (base, tone) = from_dictionary
+                .then(|| split_tone(syllable))
+                .flatten()
+                .unwrap_or_else(|| (syllable.to_lowercase(), 0));
+            // The separator sorts before every letter, so `xi an` comes
+            // before `xian`.
+            spellings.extend(base.bytes().filter(|byte| *byte > SYLLABLE_SEPARATOR));
+            spellings.push(SYLLABLE_SEPARATOR);
+            tones.push(tone + 1);
+        }
+    }
+
+    let mut key = spellings;
+    key.push(LEVEL_SEPARATOR);
+    key.extend(tones);
+    key.push(LEVEL_SEPARATOR);
+    key.extend_from_slice(original.as_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(romanized: &str, original: &str) -> Vec<u8> {
+        sort_key([(romanized, true)], original)
+    }
+
+    #[test]
+    fn shorter_syllables_sort_first() {
+        assert!(key("xi an", "西安") < key("xian", "先"));
+        assert!(key("a", "阿") < key("ai", "爱"));
+    }
+
+    #[test]
+    fn tones_break_spelling_ties() {
+        assert!(key("ma1", "妈") < key("ma3", "马"));
+        assert!(key("ma", "吗") < key("ma1", "妈"));
+    }
+
+    #[test]
+    fn original_text_breaks_reading_ties() {
+        assert!(key("zheng shuang", "郑爽") < key("zheng shuang", "鄭爽"));
+        assert!(sort_key([("Zoe", false)], "Zoe") < sort_key([("zoe", false)], "zoe"));
+    }
+}
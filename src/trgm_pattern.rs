@@ -0,0 +1,69 @@
+use crate::regex_phrase::{self, RegexTokenDictionary};
+
+const INITIAL_DIGRAPHS: [&str; 3] = ["zh", "ch", "sh"];
+
+pub fn pinyin_trgm_tokens(
+    value: &str,
+    initials: bool,
+    dictionary: &RegexTokenDictionary,
+) -> Option<Vec<String>> {
+    if !initials {
+        return regex_phrase::pinyin_query_tokens(value, dictionary);
+    }
+
+    regex_phrase::segment_query(value, |lower, idx| {
+        if INITIAL_DIGRAPHS
+            .iter()
+            .any(|digraph| lower[idx..].starts_with(digraph))
+        {
+            2
+        } else {
+            1
+        }
+    })
+    .filter(|tokens| !tokens.is_empty())
+}
+
+/// A `LIKE` prefilter for [`pinyin_trgm_regex`]: it cannot keep other
+/// syllables out from between the tokens or anchor the first one to a
+/// syllable start, so it matches a superset of the regex. Initials mode has
+/// no token long enough for a trigram and returns `None`.
+pub fn pinyin_trgm_like_pattern(
+    value: &str,
+    initials: bool,
+    dictionary: &RegexTokenDictionary,
+) -> Option<String> {
+    if initials {
+        return None;
+    }
+
+    let tokens = pinyin_trgm_tokens(value, initials, dictionary)?;
+    let mut pattern = String::with_capacity(value.len() + tokens.len() * 2 + 1);
+    pattern.push('%');
+    for (idx, token) in tokens.iter().enumerate() {
+        if idx > 0 {
+            pattern.push_str("% ");
+        }
+        pattern.push_str(token);
+    }
+    pattern.push('%');
+    Some(pattern)
+}
+
+pub fn pinyin_trgm_regex(
+    value: &str,
+    initials: bool,
+    dictionary: &RegexTokenDictionary,
+) -> Option<String> {
+    let tokens = pinyin_trgm_tokens(value, initials, dictionary)?;
+    let mut pattern = String::with_capacity(value.len() + tokens.len() * 6 + 6);
+    pattern.push_str("(^| )");
+    for (idx, token) in tokens.iter().enumerate() {
+        if idx > 0 {
+            pattern.push(' ');
+        }
+        pattern.push_str(token);
+        pattern.push_str("[^ ]*");
+    }
+    Some(pattern)
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+#[derive(Default)]
+struct PhraseTable {
+    phrases: HashMap<String, String>,
+    max_len: usize,
+}
+
+impl PhraseTable {
+    fn insert(&mut self, source: &str, target: &str) {
+        self.max_len = self.max_len.max(source.chars().count());
+        self.phrases.insert(source.to_string(), target.to_string());
+    }
+}
+
+#[derive(Default)]
+pub struct VariantDictionary {
+    ts_chars: HashMap<char, char>,
+    ts_phrases: PhraseTable,
+    st_chars: HashMap<char, char>,
+    st_phrases: PhraseTable,
+}
+
+impl VariantDictionary {
+    /// Parses `kind,source,target` rows (`ts_char`, `ts_phrase`, `st_char`,
+    /// `st_phrase`).
+    pub fn from_csv(csv_text: &str) -> Self {
+        let mut dictionary = Self::default();
+
+        for line in csv_text.lines() {
+            let mut fields = line.splitn(3, ',');
+            let (Some(kind), Some(source), Some(target)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+
+            match kind {
+                "ts_char" | "st_char" => {
+                    let (Some(source), Some(target)) = (single_char(source), single_char(target))
+                    else {
+                        continue;
+                    };
+                    if kind == "ts_char" {
+                        dictionary.ts_chars.insert(source, target);
+                    } else {
+                        dictionary.st_chars.insert(source, target);
+                    }
+                }
+                "ts_phrase" => dictionary.ts_phrases.insert(source, target),
+                "st_phrase" => dictionary.st_phrases.insert(source, target),
+                _ => {}
+            }
+        }
+
+        dictionary
+    }
+
+    /// Longest phrase match first, then the character mapping.
+    pub fn to_simplified(&self, value: &str) -> String {
+        convert(value, &self.ts_phrases, &self.ts_chars)
+    }
+
+    /// Longest phrase match first, then the default character mapping.
+    pub fn to_traditional(&self, value: &str) -> String {
+        convert(value, &self.st_phrases, &self.st_chars)
+    }
+}
+
+fn convert(value: &str, phrases: &PhraseTable, chars_map: &HashMap<char, char>) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let mut out = String::with_capacity(value.len());
+    let mut idx = 0usize;
+
+    while idx < chars.len() {
+        let max_end = usize::min(chars.len(), idx + phrases.max_len);
+        let mut matched = false;
+
+        for end in (idx + 2..=max_end).rev() {
+            let candidate: String = chars[idx..end].iter().collect();
+            if let Some(phrase) = phrases.phrases.get(&candidate) {
+                out.push_str(phrase);
+                idx = end;
+                matched = true;
+                break;
+            }
+        }
+
+        if !matched {
+            let ch = chars[idx];
+            out.push(chars_map.get(&ch).copied().unwrap_or(ch));
+            idx += 1;
+        }
+    }
+
+    out
+}
+
+fn single_char(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Some(ch),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "\
+ts_char,後,后
+ts_char,來,来
+ts_char,頭,头
+ts_char,髮,发
+ts_char,發,发
+st_char,后,後
+st_char,来,來
+st_char,头,頭
+st_char,发,發
+st_char,干,幹
+st_char,净,淨
+st_phrase,皇后,皇后
+st_phrase,头发,頭髮
+st_phrase,干净,乾淨
+st_phrase,干净利落,乾淨俐落
+";
+
+    #[test]
+    fn characters_convert_both_ways() {
+        let dictionary = VariantDictionary::from_csv(CSV);
+        assert_eq!(dictionary.to_simplified("頭髮後來"), "头发后来");
+        assert_eq!(dictionary.to_traditional("后来"), "後來");
+        assert_eq!(dictionary.to_traditional("abc"), "abc");
+    }
+
+    #[test]
+    fn longest_phrase_wins() {
+        let dictionary = VariantDictionary::from_csv(CSV);
+        assert_eq!(dictionary.to_traditional("头发"), "頭髮");
+        assert_eq!(dictionary.to_traditional("干净"), "乾淨");
+        assert_eq!(dictionary.to_traditional("干净利落"), "乾淨俐落");
+        assert_eq!(dictionary.to_traditional("皇后来"), "皇后來");
+    }
+
+    #[test]
+    fn malformed_rows_are_skipped() {
+        let dictionary = VariantDictionary::from_csv("ts_char,後後,后\nunknown,a,b\nts_char\n");
+        assert_eq!(dictionary.to_simplified("後"), "後");
+    }
+}
@@ -0,0 +1,421 @@
+use std::borrow::Cow;
+
+const INITIALS: [(&str, char); 21] = [
+    ("zh", 'ㄓ'),
+    ("ch", 'ㄔ'),
+    ("sh", 'ㄕ'),
+    ("b", 'ㄅ'),
+    ("p", 'ㄆ'),
+    ("m", 'ㄇ'),
+    ("f", 'ㄈ'),
+    ("d", 'ㄉ'),
+    ("t", 'ㄊ'),
+    ("n", 'ㄋ'),
+    ("l", 'ㄌ'),
+    ("g", 'ㄍ'),
+    ("k", 'ㄎ'),
+    ("h", 'ㄏ'),
+    ("j", 'ㄐ'),
+    ("q", 'ㄑ'),
+    ("x", 'ㄒ'),
+    ("r", 'ㄖ'),
+    ("z", 'ㄗ'),
+    ("c", 'ㄘ'),
+    ("s", 'ㄙ'),
+];
+
+// Finals in their standalone spelling after y/w rewriting, with `v` for `ü`.
+// `ong` comes before `ueng` so Bopomofo `ㄨㄥ` after an initial reads as `ong`.
+const FINALS: [(&str, &str); 36] = [
+    ("a", "ㄚ"),
+    ("o", "ㄛ"),
+    ("e", "ㄜ"),
+    ("ai", "ㄞ"),
+    ("ei", "ㄟ"),
+    ("ao", "ㄠ"),
+    ("ou", "ㄡ"),
+    ("an", "ㄢ"),
+    ("en", "ㄣ"),
+    ("ang", "ㄤ"),
+    ("eng", "ㄥ"),
+    ("er", "ㄦ"),
+    ("ong", "ㄨㄥ"),
+    ("i", "ㄧ"),
+    ("ia", "ㄧㄚ"),
+    ("io", "ㄧㄛ"),
+    ("ie", "ㄧㄝ"),
+    ("iao", "ㄧㄠ"),
+    ("iu", "ㄧㄡ"),
+    ("ian", "ㄧㄢ"),
+    ("in", "ㄧㄣ"),
+    ("iang", "ㄧㄤ"),
+    ("ing", "ㄧㄥ"),
+    ("iong", "ㄩㄥ"),
+    ("u", "ㄨ"),
+    ("ua", "ㄨㄚ"),
+    ("uo", "ㄨㄛ"),
+    ("uai", "ㄨㄞ"),
+    ("ui", "ㄨㄟ"),
+    ("uan", "ㄨㄢ"),
+    ("un", "ㄨㄣ"),
+    ("uang", "ㄨㄤ"),
+    ("v", "ㄩ"),
+    ("ve", "ㄩㄝ"),
+    ("van", "ㄩㄢ"),
+    ("vn", "ㄩㄣ"),
+];
+
+const TONE_MARKS: [(char, char, u8); 24] = [
+    ('ā', 'a', 1),
+    ('á', 'a', 2),
+    ('ǎ', 'a', 3),
+    ('à', 'a', 4),
+    ('ō', 'o', 1),
+    ('ó', 'o', 2),
+    ('ǒ', 'o', 3),
+    ('ò', 'o', 4),
+    ('ē', 'e', 1),
+    ('é', 'e', 2),
+    ('ě', 'e', 3),
+    ('è', 'e', 4),
+    ('ī', 'i', 1),
+    ('í', 'i', 2),
+    ('ǐ', 'i', 3),
+    ('ì', 'i', 4),
+    ('ū', 'u', 1),
+    ('ú', 'u', 2),
+    ('ǔ', 'u', 3),
+    ('ù', 'u', 4),
+    ('ǖ', 'v', 1),
+    ('ǘ', 'v', 2),
+    ('ǚ', 'v', 3),
+    ('ǜ', 'v', 4),
+];
+
+const NEUTRAL_TONE: char = '˙';
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputStyle {
+    #[default]
+    Pinyin,
+    /// Bopomofo, e.g. `ㄓㄥ ㄕㄨㄤ`.
+    Zhuyin,
+}
+
+impl OutputStyle {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pinyin" => Some(Self::Pinyin),
+            "zhuyin" | "bopomofo" => Some(Self::Zhuyin),
+            _ => None,
+        }
+    }
+}
+
+/// Converts one pinyin syllable to Bopomofo.
+///
+/// Accepts toneless (`zheng`), tone-number (`zheng4`) and tone-mark (`zhèng`)
+/// spellings; `v` and `ü` both read as `ü`. Returns `None` for anything that
+/// is not a pinyin syllable.
+pub fn syllable_to_zhuyin(syllable: &str) -> Option<String> {
+    let (base, tone) = split_tone(syllable)?;
+    let (initial, final_) = split_syllable(&base)?;
+
+    let mut out = String::with_capacity(12);
+    if tone == 5 {
+        out.push(NEUTRAL_TONE);
+    }
+    if let Some(initial) = initial {
+        out.push(initial_zhuyin(initial)?);
+    }
+    if !final_.is_empty() {
+        out.push_str(final_zhuyin(&final_)?);
+    }
+    if let Some(mark) = tone_mark(tone) {
+        out.push(mark);
+    }
+    Some(out)
+}
+
+/// Converts every whitespace-separated syllable of a romanized phrase,
+/// keeping anything that is not a pinyin syllable unchanged.
+pub fn phrase_to_zhuyin(phrase: &str) -> String {
+    phrase
+        .split_whitespace()
+        .map(|syllable| syllable_to_zhuyin(syllable).unwrap_or_else(|| syllable.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn is_zhuyin_char(ch: char) -> bool {
+    ('\u{3105}'..='\u{312F}').contains(&ch) || ('\u{31A0}'..='\u{31BF}').contains(&ch)
+}
+
+/// Replaces Bopomofo syllables with toneless pinyin separated by spaces
+/// (`ㄓㄥˋ ㄕㄨㄤˇ` -> `zheng shuang`). `ㄩ` is written as `v` after `ㄋ` and
+/// `ㄌ` (`ㄋㄩ` -> `nv`), like the bundled dictionary, and as `u` after
+/// `ㄐㄑㄒ`; a lone initial becomes its pinyin initial. Text without Bopomofo
+/// is returned unchanged; characters that do not form a syllable are kept as
+/// they are.
+pub fn zhuyin_to_pinyin(value: &str) -> Cow<'_, str> {
+    if !value.chars().any(is_zhuyin_char) {
+        return Cow::Borrowed(value);
+    }
+
+    let chars: Vec<char> = value.chars().collect();
+    let mut out = String::with_capacity(value.len());
+    let mut idx = 0usize;
+
+    while idx < chars.len() {
+        match parse_zhuyin_syllable(&chars[idx..]) {
+            Some((pinyin, consumed)) => {
+                if !out.is_empty() && !out.ends_with(' ') {
+                    out.push(' ');
+                }
+                out.push_str(&pinyin);
+                out.push(' ');
+                idx += consumed;
+            }
+            None => {
+                let ch = chars[idx];
+                let repeated_space = ch.is_whitespace() && out.ends_with(' ');
+                if !repeated_space && !is_tone_char(ch) {
+                    out.push(ch);
+                }
+                idx += 1;
+            }
+        }
+    }
+
+    Cow::Owned(out.trim().to_string())
+}
+
+pub(crate) fn split_tone(syllable: &str) -> Option<(String, u8)> {
+    let (letters, mut tone) = match syllable.char_indices().last() {
+        Some((idx, digit @ '1'..='5')) => (&syllable[..idx], digit as u8 - b'0'),
+        _ => (syllable, 0u8),
+    };
+    let numbered = tone != 0;
+    let mut base = String::with_capacity(letters.len());
+
+    for ch in letters.chars() {
+        let lower = ch.to_lowercase().next().unwrap_or(ch);
+        if let Some(&(_, plain, mark)) = TONE_MARKS.iter().find(|(marked, _, _)| *marked == lower) {
+            if numbered {
+                return None;
+            }
+            tone = mark;
+            base.push(plain);
+        } else if lower == 'ü' {
+            base.push('v');
+        } else if lower.is_ascii_lowercase() {
+            base.push(lower);
+        } else {
+            return None;
+        }
+    }
+
+    if base.is_empty() {
+        None
+    } else {
+        Some((base, tone))
+    }
+}
+
+pub(crate) fn split_syllable(base: &str) -> Option<(Option<&'static str>, String)> {
+    let (initial, rest) = match INITIALS
+        .iter()
+        .find(|(initial, _)| base.starts_with(initial))
+    {
+        Some((initial, _)) if base.len() > initial.len() => {
+            (Some(*initial), &base[initial.len()..])
+        }
+        _ => (None, base),
+    };
+
+    if initial.is_none() && rest.len() > 1 {
+        if let Some(rest) = rest.strip_prefix('y') {
+            let final_ = match rest {
+                "i" | "in" | "ing" => rest.to_string(),
+                "u" | "ue" | "uan" | "un" => format!("v{}", &rest[1..]),
+                "ou" => "iu".to_string(),
+                _ => format!("i{rest}"),
+            };
+            return Some((None, final_));
+        }
+        if let Some(rest) = rest.strip_prefix('w') {
+            let final_ = match rest {
+                "u" => "u".to_string(),
+                "ei" => "ui".to_string(),
+                "en" => "un".to_string(),
+                _ => format!("u{rest}"),
+            };
+            return Some((None, final_));
+        }
+    }
+
+    let final_ = match initial {
+        Some("j" | "q" | "x") if rest.starts_with('u') => format!("v{}", &rest[1..]),
+        Some("n" | "l") if rest == "ue" => "ve".to_string(),
+        Some("zh" | "ch" | "sh" | "r" | "z" | "c" | "s") if rest == "i" => String::new(),
+        _ => rest.to_string(),
+    };
+    Some((initial, final_))
+}
+
+fn initial_zhuyin(initial: &str) -> Option<char> {
+    INITIALS
+        .iter()
+        .find(|(pinyin, _)| *pinyin == initial)
+        .map(|&(_, zhuyin)| zhuyin)
+}
+
+fn final_zhuyin(final_: &str) -> Option<&'static str> {
+    if final_ == "ueng" {
+        return Some("ㄨㄥ");
+    }
+    FINALS
+        .iter()
+        .find(|(pinyin, _)| *pinyin == final_)
+        .map(|&(_, zhuyin)| zhuyin)
+}
+
+/// Whether `final_` (as returned by `split_syllable`) is a pinyin final; the
+/// empty final of `zhi`, `chi`, `shi`, `ri`, `zi`, `ci` and `si` counts.
+pub(crate) fn is_final(final_: &str) -> bool {
+    final_.is_empty() || final_zhuyin(final_).is_some()
+}
+
+fn tone_mark(tone: u8) -> Option<char> {
+    match tone {
+        2 => Some('ˊ'),
+        3 => Some('ˇ'),
+        4 => Some('ˋ'),
+        _ => None,
+    }
+}
+
+fn is_tone_char(ch: char) -> bool {
+    matches!(ch, 'ˉ' | 'ˊ' | 'ˇ' | 'ˋ' | NEUTRAL_TONE)
+}
+
+fn parse_zhuyin_syllable(chars: &[char]) -> Option<(String, usize)> {
+    let mut idx = 0usize;
+    if chars.first() == Some(&NEUTRAL_TONE) {
+        idx += 1;
+    }
+
+    let initial = chars.get(idx).and_then(|&ch| {
+        INITIALS
+            .iter()
+            .find(|&&(_, zhuyin)| zhuyin == ch)
+            .map(|&(pinyin, _)| pinyin)
+    });
+    if initial.is_some() {
+        idx += 1;
+    }
+
+    // Longest final first: a medial (`ㄧㄨㄩ`) and a rhyme, or either alone.
+    let (final_, final_len) = [2usize, 1]
+        .into_iter()
+        .filter(|&len| idx + len <= chars.len())
+        .find_map(|len| {
+            let candidate: String = chars[idx..idx + len].iter().collect();
+            final_pinyin(&candidate, initial.is_some()).map(|final_| (final_, len))
+        })
+        .unwrap_or(("", 0));
+    idx += final_len;
+
+    if initial.is_none() && final_.is_empty() {
+        return None;
+    }
+    if chars.get(idx).copied().is_some_and(is_tone_char) {
+        idx += 1;
+    }
+
+    // A lone initial stays a prefix (`ㄓ` -> `zh`), which also matches `zhi`.
+    let pinyin = match (initial, final_) {
+        (Some(initial), "") => initial.to_string(),
+        // Pinyin only writes `ü` after `n` and `l`; `ㄐㄩ` is `ju`.
+        (Some(initial @ ("n" | "l")), final_) => format!("{initial}{final_}"),
+        (Some(initial), final_) => format!("{initial}{}", final_.replace('v', "u")),
+        (None, final_) => standalone_final(final_),
+    };
+    Some((pinyin, idx))
+}
+
+fn final_pinyin(zhuyin: &str, has_initial: bool) -> Option<&'static str> {
+    if !has_initial && zhuyin == "ㄨㄥ" {
+        return Some("ueng");
+    }
+    FINALS
+        .iter()
+        .find(|(_, candidate)| *candidate == zhuyin)
+        .map(|&(pinyin, _)| pinyin)
+}
+
+fn standalone_final(final_: &str) -> String {
+    match final_ {
+        "i" | "in" | "ing" => format!("y{final_}"),
+        "iu" => "you".to_string(),
+        "u" => "wu".to_string(),
+        "ui" => "wei".to_string(),
+        "un" => "wen".to_string(),
+        _ => {
+            if let Some(rest) = final_.strip_prefix('i') {
+                format!("y{rest}")
+            } else if let Some(rest) = final_.strip_prefix('u') {
+                format!("w{rest}")
+            } else if let Some(rest) = final_.strip_prefix('v') {
+                format!("yu{rest}")
+            } else {
+                final_.to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syllables_convert_to_zhuyin() {
+        assert_eq!(syllable_to_zhuyin("zheng4").as_deref(), Some("ㄓㄥˋ"));
+        assert_eq!(syllable_to_zhuyin("shuǎng").as_deref(), Some("ㄕㄨㄤˇ"));
+        assert_eq!(syllable_to_zhuyin("zhi").as_deref(), Some("ㄓ"));
+        assert_eq!(syllable_to_zhuyin("lv4").as_deref(), Some("ㄌㄩˋ"));
+        assert_eq!(syllable_to_zhuyin("ju").as_deref(), Some("ㄐㄩ"));
+        assert_eq!(syllable_to_zhuyin("weng").as_deref(), Some("ㄨㄥ"));
+        assert_eq!(syllable_to_zhuyin("ma5").as_deref(), Some("˙ㄇㄚ"));
+        assert_eq!(syllable_to_zhuyin("hello"), None);
+    }
+
+    #[test]
+    fn phrases_keep_unknown_parts() {
+        assert_eq!(phrase_to_zhuyin("zheng shuang abc"), "ㄓㄥ ㄕㄨㄤ abc");
+    }
+
+    #[test]
+    fn zhuyin_reads_back_as_pinyin() {
+        assert_eq!(zhuyin_to_pinyin("ㄓㄥˋㄕㄨㄤˇ"), "zheng shuang");
+        assert_eq!(zhuyin_to_pinyin("ㄋㄩ ㄌㄩㄝ ㄐㄩ"), "nv lve ju");
+        assert!(matches!(zhuyin_to_pinyin("zheng"), Cow::Borrowed("zheng")));
+    }
+
+    #[test]
+    fn tones_split_from_syllables() {
+        assert_eq!(split_tone("zhèng"), Some(("zheng".to_string(), 4)));
+        assert_eq!(split_tone("lü3"), Some(("lv".to_string(), 3)));
+        assert_eq!(split_tone("zhèng4"), None);
+        assert_eq!(split_tone("5"), None);
+    }
+
+    #[test]
+    fn finals_include_the_empty_final() {
+        assert!(is_final(""));
+        assert!(is_final("ueng"));
+        assert!(is_final("vn"));
+        assert!(!is_final("ello"));
+    }
+}
@@ -9,7 +9,19 @@ TRUNCATE TABLE pinyin.pinyin_words;
 INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES
   (' ', ' '),
   ('郑', '|zheng|'),
-  ('爽', '|shuang|');
+  ('爽', '|shuang|'),
+  ('一', '|yi|'),
+  ('二', '|er|'),
+  ('三', '|san|'),
+  ('十', '|shi|'),
+  ('百', '|bai|'),
+  ('单', '|dan|chan|shan|'),
+  ('田', '|tian|'),
+  ('女', '|nv|ru|'),
+  ('努', '|nu|'),
+  ('绿', '|lv|'),
+  ('吕', '|lv|'),
+  ('略', '|lve|');
 
 INSERT INTO pinyin.pinyin_words (word, pinyin)
 VALUES ('郑爽', '|zheng| |shuang|')
@@ -29,12 +41,13 @@ TRUNCATE TABLE pinyin.pinyin_mapping_suffix1;
 TRUNCATE TABLE pinyin.pinyin_words_suffix1;
 
 INSERT INTO pinyin.pinyin_mapping_suffix1 (character, pinyin) VALUES
-  ('郑', '|zhengx|');
+  ('郑', '|zhengx|'),
+  ('鄭', '|zheng|');
 
 INSERT INTO pinyin.pinyin_words_suffix1 (word, pinyin) VALUES
   ('郑爽', '|zhengx| |shuangx|');
 
-SELECT plan(11);
+SELECT plan(78);
 
 SELECT is(
   public.pinyin_char_romanize('郑爽ABC'),
@@ -117,6 +130,463 @@ SELECT is(
   'pinyin_word_romanize(anyelement,text) is immutable'
 );
 
+SELECT is(
+  public.pinyin_trgm_pattern('zhengs'),
+  '%zheng% s%',
+  'pinyin_trgm_pattern inserts syllable separators'
+);
+
+SELECT ok(
+  public.pinyin_char_romanize('郑爽ABC') ~ public.pinyin_trgm_regex('zs', true),
+  'pinyin_trgm_regex initials variant matches char romanization'
+);
+
+SELECT is(
+  public.pinyin_trgm_regex('郑s'),
+  NULL::text,
+  'pinyin_trgm_regex returns NULL for non-pinyin input'
+);
+
+SELECT results_eq(
+  $$SELECT source, char_start, char_end, byte_start, byte_end, pinyin, kind
+    FROM public.pinyin_romanize_tokens('郑爽ABC')
+    ORDER BY ord$$,
+  $$VALUES ('郑爽', 0, 2, 0, 6, 'zheng shuang', 'word'),
+           ('ABC', 2, 5, 6, 9, 'abc', 'passthrough')$$,
+  'pinyin_romanize_tokens keeps source offsets per output token'
+);
+
+SELECT results_eq(
+  $$SELECT source, pinyin, kind
+    FROM public.pinyin_romanize_tokens('郑爽ABC', '_suffix1')
+    ORDER BY ord$$,
+  $$VALUES ('郑爽', 'zhengx shuangx', 'word'),
+           ('ABC', 'abc', 'passthrough')$$,
+  'pinyin_romanize_tokens uses suffix dictionaries'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('郑爽，ABC！'),
+  'zheng shuang abc',
+  'punctuation is dropped by default'
+);
+
+SELECT is(
+  public.pinyin_char_romanize('郑爽，ABC', '', '{"punctuation": "keep", "normalize": "nfkc"}'),
+  'zheng shuang , abc',
+  'punctuation keep policy emits punctuation tokens'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('郑爽ＡＢＣ', '', '{"normalize": "nfkc"}'),
+  public.pinyin_word_romanize('郑爽ABC'),
+  'normalize nfkc folds full-width letters before romanization'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('郑爽ＡＢＣ'),
+  'zheng shuang',
+  'full-width letters are not normalized by default'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('Café 郑爽', '', '{"fold_latin": true}'),
+  'cafe zheng shuang',
+  'accented Latin letters fold to ASCII'
+);
+
+SELECT is(
+  public.pinyin_char_romanize('zhèng shuǎng lǜ', '', '{"fold_latin": true}'),
+  'zheng shuang lu',
+  'pinyin tone marks are stripped'
+);
+
+SELECT is(
+  public.pinyin_to_simplified('鄭爽 頭髮'),
+  '郑爽 头发',
+  'pinyin_to_simplified converts Traditional characters'
+);
+
+SELECT is(
+  public.pinyin_to_traditional('头发 皇后'),
+  '頭髮 皇后',
+  'pinyin_to_traditional prefers phrase mappings'
+);
+
+SELECT is(
+  public.pinyin_to_simplified('裏面 上昇 後來 頭髮'),
+  '里面 上升 后来 头发',
+  'pinyin_to_simplified maps known Traditional pairs'
+);
+
+SELECT is(
+  public.pinyin_to_traditional('上升 后来 万俟 头发'),
+  '上升 後來 万俟 頭髮',
+  'pinyin_to_traditional keeps phrase exceptions'
+);
+
+SELECT is(
+  public.pinyin_to_simplified(public.pinyin_to_traditional('头发 后来 皇后 上升 干净')),
+  '头发 后来 皇后 上升 干净',
+  'Simplified text round-trips through Traditional'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('鄭爽', '_suffix1', '{"variant_fallback": true}'),
+  'zhengx shuangx',
+  'variant_fallback matches words written in the other script'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('郑爽ABC', '', '{"style": "zhuyin"}'),
+  'ㄓㄥ ㄕㄨㄤ abc',
+  'zhuyin style converts dictionary readings to Bopomofo'
+);
+
+SELECT is(
+  public.pinyin_char_romanize('女绿吕略努', '', '{"style": "zhuyin"}'),
+  'ㄋㄩ ㄌㄩ ㄌㄩ ㄌㄩㄝ ㄋㄨ',
+  'zhuyin style keeps ü for nü/lü readings'
+);
+
+SELECT is(
+  public.pinyin_char_romanize('女绿吕略努'),
+  'nv lv lv lve nu',
+  'pinyin output writes ü as v'
+);
+
+SELECT is(
+  public.pinyin_regex_phrase_patterns('ㄋㄩ ㄌㄩㄝ ㄋㄨ'),
+  ARRAY['nv.*', 'lve.*', 'nu.*']::text[],
+  'Bopomofo ㄩ reads back as v'
+);
+
+SELECT is(
+  public.pinyin_regex_phrase_patterns('ㄓㄕㄨㄤ'),
+  ARRAY['zh.*', 'shuang.*']::text[],
+  'pinyin_regex_phrase_patterns accepts Bopomofo input'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('郑爽ABC', '', '{"system": "wade_giles"}'),
+  'chêng shuang abc',
+  'wade_giles system converts dictionary readings'
+);
+
+SELECT is(
+  public.pinyin_regex_phrase_patterns('Cheng Shuang', false, 'wade_giles'),
+  ARRAY['zheng.*', 'shuang.*']::text[],
+  'Wade-Giles query is converted to pinyin patterns'
+);
+
+SELECT is(
+  public.pinyin_regex_phrase_patterns('zheng shuang', false, 'pinyin', 'yale'),
+  ARRAY['jeng.*', 'shwang.*']::text[],
+  'pinyin query is converted to Yale patterns'
+);
+
+SELECT is(
+  public.pinyin_char_romanize('郑爽ABC', '', '{"separator": "", "case": "title"}'),
+  'ZhengShuangAbc',
+  'separator and title case are applied per syllable'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('郑爽ABC', '', '{"join": "word", "separator": "-"}'),
+  'zhengshuang-abc',
+  'word join keeps the syllables of a dictionary word together'
+);
+
+SELECT is(
+  public.pinyin_slugify('郑爽，ＡＢＣ！'),
+  'zheng-shuang-abc',
+  'pinyin_slugify builds a hyphenated ASCII slug'
+);
+
+SELECT is(
+  public.pinyin_slugify('郑爽ABC', 13, '_suffix1'),
+  'zhengx',
+  'pinyin_slugify truncates at a syllable boundary with a suffix dictionary'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('郑爽123', '', '{"numerals": "read"}'),
+  'zheng shuang yi bai er shi san',
+  'numerals read mode reads Arabic numbers in Mandarin'
+);
+
+SELECT is(
+  public.pinyin_word_romanize('一百二十三', '', '{"numerals": "arabic"}'),
+  '123',
+  'numerals arabic mode writes Chinese numerals as digits'
+);
+
+SELECT ok(
+  public.pinyin_sort_key('Zoe') < public.pinyin_sort_key('zoe')
+    AND public.pinyin_sort_key('郑爽') < public.pinyin_sort_key('Zoe'),
+  'pinyin_sort_key orders by reading, then by original text'
+);
+
+SELECT is(
+  public.pinyin_sort_bucket('郑爽'),
+  'Z',
+  'pinyin_sort_bucket returns the index-bar letter'
+);
+
+SELECT is(
+  (SELECT array_agg(public.pinyin_sort_bucket(name) ORDER BY name)
+   FROM unnest(ARRAY['郑爽', 'abc', '123', '9号', '，我们', '']) AS name),
+  (SELECT array_agg(public.pinyin_first_letter(name) ORDER BY name)
+   FROM unnest(ARRAY['郑爽', 'abc', '123', '9号', '，我们', '']) AS name),
+  'pinyin_sort_bucket matches pinyin_first_letter with default options'
+);
+
+SELECT is(
+  public.pinyin_first_letter('郑爽'),
+  'Z',
+  'pinyin_first_letter returns the uppercase initial'
+);
+
+SELECT is(
+  public.pinyin_first_letter('123', '', '{"digits": "keep"}'),
+  '1',
+  'pinyin_first_letter can keep leading digits'
+);
+
+SELECT is(
+  public.pinyin_name_romanize('单田'),
+  'shan tian',
+  'pinyin_name_romanize reads polyphonic surnames with their surname reading'
+);
+
+SELECT is(
+  public.pinyin_similarity('郑爽', 'zheng shuang'),
+  1.0::double precision,
+  'pinyin_similarity compares readings, not characters'
+);
+
+SELECT ok(
+  ('郑爽'::text <~> 'zeng shuang') < ('郑爽'::text <~> 'tian shuang'),
+  'confusable initials are closer than unrelated ones'
+);
+
+SELECT ok(
+  EXISTS (SELECT 1 FROM public.pinyin_homophones('shan') WHERE term = '单' AND kind = 'char'),
+  'pinyin_homophones lists characters under every reading'
+);
+
+SELECT ok(
+  NOT EXISTS (SELECT 1 FROM public.pinyin_homophones('郑') WHERE term = '郑'),
+  'pinyin_homophones leaves out the input itself'
+);
+
+SELECT is(
+  (SELECT candidate FROM public.pinyin_to_hanzi('zhengshuang', 1)),
+  '郑爽',
+  'pinyin_to_hanzi prefers whole dictionary words'
+);
+
+SELECT throws_ok(
+  $$SELECT * FROM public.pinyin_to_hanzi('zheng', 0)$$,
+  'pinyin_to_hanzi limit must be positive',
+  'pinyin_to_hanzi rejects a non-positive limit'
+);
+
+SELECT is(
+  public.pinyin_word_romanize_batch(ARRAY['郑爽', NULL]),
+  ARRAY['zheng shuang', NULL],
+  'pinyin_word_romanize_batch romanizes each element and keeps NULLs'
+);
+
+SELECT is(
+  public.pinyin_char_romanize(ARRAY['郑爽']),
+  ARRAY[public.pinyin_char_romanize('郑爽')],
+  'pinyin_char_romanize(text[]) matches the single-value call'
+);
+
+SELECT is(
+  public.pinyin_char_romanize_batch(ARRAY['郑爽', NULL], '', '{"case": "title"}'),
+  public.pinyin_char_romanize(ARRAY['郑爽', NULL], '', '{"case": "title"}'),
+  'pinyin_char_romanize_batch matches the pinyin_char_romanize(text[]) overload'
+);
+
+CREATE TEMP TABLE pinyin_maintained (name text, name_pinyin text);
+CREATE TRIGGER pinyin_maintained_name
+BEFORE INSERT OR UPDATE ON pinyin_maintained
+FOR EACH ROW EXECUTE FUNCTION pinyin.maintain_columns('name', 'name_pinyin', 'word');
+INSERT INTO pinyin_maintained (name) VALUES ('郑爽');
+
+SELECT is(
+  (SELECT name_pinyin FROM pinyin_maintained),
+  'zheng shuang',
+  'pinyin.maintain_columns sets the romanized column'
+);
+
+SELECT throws_ok(
+  $$SELECT pinyin.romanize_column_value('郑爽', 'nope')$$,
+  'romanize mode must be one of char, word, initials (got "nope")',
+  'unknown maintain_columns mode is rejected'
+);
+
+SELECT throws_ok(
+  $$CALL public.pinyin_backfill('pinyin_maintained', 'name', 'name_pinyin', 'word')$$,
+  'pinyin_backfill cannot run inside a transaction block',
+  'pinyin_backfill refuses to run inside a transaction block'
+);
+
+CREATE TEMP VIEW pinyin_maintained_view AS SELECT * FROM pinyin_maintained;
+
+SELECT throws_ok(
+  $$CALL public.pinyin_backfill('pinyin_maintained_view', 'name', 'name_pinyin')$$,
+  'pinyin_backfill target pinyin_maintained_view must be a table or a partitioned table',
+  'pinyin_backfill rejects targets without storage'
+);
+
+SELECT public.pinyin_register_column('pinyin_maintained', 'name', 'name_pinyin', 'word');
+UPDATE pinyin.pinyin_romanized_columns
+SET refreshed_version = (SELECT version FROM pinyin.pinyin_dictionary_meta);
+UPDATE pinyin.pinyin_mapping SET pinyin = '|tian|dian|' WHERE character = '田';
+
+SELECT is(
+  (
+    SELECT key
+    FROM pinyin.pinyin_dictionary_changes
+    WHERE version = (SELECT version FROM pinyin.pinyin_dictionary_meta)
+  ),
+  '田',
+  'dictionary changes are logged under the bumped version'
+);
+
+SELECT is(
+  (SELECT changed_keys FROM public.pinyin_stale_columns() WHERE target = 'pinyin_maintained'::regclass),
+  1::bigint,
+  'pinyin_stale_columns counts changed keys since the last refresh'
+);
+
+SELECT is(
+  (
+    SELECT concat_ws(' ', d.key, d.old_value, d.new_value, d.change)
+    FROM public.pinyin_dictionary_diff(
+      (SELECT refreshed_version FROM pinyin.pinyin_romanized_columns),
+      (SELECT version FROM pinyin.pinyin_dictionary_meta)
+    ) AS d
+  ),
+  '田 |tian| |tian|dian| changed',
+  'pinyin_dictionary_diff reports the old and new reading'
+);
+
+SELECT is(
+  (
+    SELECT count(*)
+    FROM public.pinyin_affected_rows('pinyin_maintained', 'name', 'name_pinyin', ARRAY['爽'], 'word')
+  ),
+  0::bigint,
+  'pinyin_affected_rows skips rows whose stored value is current'
+);
+
+ALTER TABLE pinyin_maintained DISABLE TRIGGER pinyin_maintained_name;
+UPDATE pinyin_maintained SET name_pinyin = 'zheng shuangx';
+ALTER TABLE pinyin_maintained ENABLE TRIGGER pinyin_maintained_name;
+
+SELECT is(
+  (
+    SELECT array_agg(concat_ws(':', source, stored, romanized))
+    FROM public.pinyin_affected_rows('pinyin_maintained', 'name', 'name_pinyin', ARRAY['爽'], 'word')
+  ),
+  ARRAY['郑爽:zheng shuangx:zheng shuang']::text[],
+  'pinyin_affected_rows finds stale rows containing a changed key'
+);
+
+UPDATE pinyin_maintained SET name = name;
+
+INSERT INTO pinyin.pinyin_word_frequency (word, frequency) VALUES ('我们', 10);
+
+SELECT is(
+  (SELECT changed_keys FROM public.pinyin_stale_columns() WHERE target = 'pinyin_maintained'::regclass),
+  1::bigint,
+  'word frequency changes do not count as changed keys'
+);
+
+SELECT is(
+  public.pinyin_track_suffix_tables('_suffix1'),
+  2,
+  'pinyin_track_suffix_tables attaches change logging to existing overlay tables'
+);
+
+UPDATE pinyin.pinyin_mapping_suffix1 SET pinyin = '|zhengz|' WHERE character = '鄭';
+
+SELECT is(
+  pinyin.dictionary_changed_keys(
+    (SELECT refreshed_version FROM pinyin.pinyin_romanized_columns),
+    (SELECT version FROM pinyin.pinyin_dictionary_meta),
+    'char',
+    '_suffix1'
+  ),
+  ARRAY['田', '鄭']::text[],
+  'overlay changes count only for registrations with that suffix'
+);
+
+SELECT throws_ok(
+  $$CALL public.pinyin_refresh_columns()$$,
+  'pinyin_refresh_columns cannot run inside a transaction block',
+  'pinyin_refresh_columns refuses to run inside a transaction block'
+);
+
+SELECT public.pinyin_prune_dictionary_changes();
+
+SELECT is(
+  (
+    SELECT count(*)
+    FROM pinyin.pinyin_dictionary_changes
+    WHERE version <= (SELECT refreshed_version FROM pinyin.pinyin_romanized_columns)
+  ),
+  0::bigint,
+  'pinyin_prune_dictionary_changes drops changes every column has caught up to'
+);
+
+SELECT throws_ok(
+  $$SELECT public.pinyin_word_romanize('郑爽', '', '{"punctuation": "nope"}')$$,
+  'romanize option "punctuation" must be one of drop, keep, separator',
+  'invalid punctuation policy is rejected'
+);
+
+SELECT is(
+  public.pinyin_char_romanize_array('郑爽ABC', '_suffix1'),
+  ARRAY['zhengy', 'shuang', 'abc']::text[],
+  'pinyin_char_romanize_array returns one element per syllable'
+);
+
+SELECT is(
+  public.pinyin_word_romanize_array('郑爽ABC'),
+  ARRAY['zheng shuang', 'abc']::text[],
+  'pinyin_word_romanize_array returns one element per word'
+);
+
+SELECT is(
+  public.pinyin_romanize_jsonb('郑爽ABC'),
+  '[{"source": "郑爽", "pinyin": "zheng shuang", "kind": "word", "char_start": 0, "char_end": 2, "byte_start": 0, "byte_end": 6},
+    {"source": "ABC", "pinyin": "abc", "kind": "passthrough", "char_start": 2, "char_end": 5, "byte_start": 6, "byte_end": 9}]'::jsonb,
+  'pinyin_romanize_jsonb returns per-token metadata'
+);
+
+SELECT is(
+  public.pinyin_highlight('我爱郑爽ABC', 'zhengs'),
+  '我爱<b>郑爽</b>ABC',
+  'pinyin_highlight wraps the Han characters matched by a pinyin query'
+);
+
+SELECT is(
+  public.pinyin_highlight('郑爽ABC', 'shuang abc', '[', ']'),
+  '郑[爽ABC]',
+  'pinyin_highlight merges adjacent matches and accepts custom tags'
+);
+
+SELECT is(
+  public.pinyin_highlight('郑爽ABC', 'zhengx', '[', ']', '_suffix1'),
+  '[郑]爽ABC',
+  'pinyin_highlight uses suffix dictionaries'
+);
+
 SELECT * FROM finish();
 
 ROLLBACK;
@@ -0,0 +1,62 @@
+BEGIN;
+
+CREATE EXTENSION IF NOT EXISTS pgtap;
+CREATE EXTENSION IF NOT EXISTS pg_pinyin;
+
+TRUNCATE TABLE pinyin.pinyin_mapping;
+TRUNCATE TABLE pinyin.pinyin_words;
+
+INSERT INTO pinyin.pinyin_mapping (character, pinyin) VALUES
+  (' ', ' '),
+  ('我', '|wo|'),
+  ('郑', '|zheng|'),
+  ('爽', '|shuang|');
+
+INSERT INTO pinyin.pinyin_words (word, pinyin)
+VALUES ('郑爽', '|zheng| |shuang|')
+ON CONFLICT (word) DO UPDATE SET pinyin = EXCLUDED.pinyin;
+
+SELECT plan(6);
+
+SELECT is(
+  (SELECT array_agg(alias ORDER BY tokid) FROM ts_token_type('pinyin_parser')),
+  ARRAY['asciiword', 'han', 'blank']::text[],
+  'pinyin_parser exposes asciiword, han and blank token types'
+);
+
+SELECT is(
+  to_tsvector('pinyin', '我郑爽ABC')::text,
+  '''abc'':3 ''w'':1 ''wo'':1 ''zhengshuang'':2 ''zs'':2 ''我'':1 ''郑爽'':2',
+  'to_tsvector emits Han words, pinyin and initials at word positions'
+);
+
+SELECT ok(
+  to_tsvector('pinyin', '我郑爽ABC') @@ to_tsquery('pinyin', 'zhengshuang <-> abc'),
+  'pinyin tsquery matches joined word pinyin'
+);
+
+SELECT ok(
+  to_tsvector('pinyin', '我郑爽ABC') @@ plainto_tsquery('pinyin', '郑爽'),
+  'Han tsquery matches any variant of the word'
+);
+
+SELECT is(
+  ts_lexize('pinyin_dict', '郑爽'),
+  ARRAY['郑爽', 'zhengshuang', 'zs']::text[],
+  'pinyin_dict lexizes a Han word into word, pinyin and initials'
+);
+
+CREATE TEXT SEARCH DICTIONARY pinyin_dict_no_initials (
+  TEMPLATE = pinyin_template,
+  initials = false
+);
+
+SELECT is(
+  ts_lexize('pinyin_dict_no_initials', '郑爽'),
+  ARRAY['郑爽', 'zhengshuang']::text[],
+  'initials option disables initials lexemes'
+);
+
+SELECT * FROM finish();
+
+ROLLBACK;